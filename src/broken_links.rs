@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A `refid://` link whose target is not part of the generated documentation.
+#[derive(Clone)]
+pub struct BrokenLink {
+    /// HTML file containing the link
+    pub page: String,
+    /// Source file the page was generated from
+    pub source: String,
+    /// Qualified name of the member the link appears in, if any
+    pub member: Option<String>,
+    pub ref_id: String,
+    pub text: String,
+}
+
+impl BrokenLink {
+    pub fn to_json(&self) -> json::JsonValue {
+        json::object! {
+            page: self.page.as_str(),
+            source: self.source.as_str(),
+            member: self.member.as_deref(),
+            ref_id: self.ref_id.as_str(),
+            text: self.text.as_str(),
+        }
+    }
+
+    pub fn from_json(value: &json::JsonValue) -> Option<BrokenLink> {
+        Some(BrokenLink {
+            page: value["page"].as_str()?.to_owned(),
+            source: value["source"].as_str()?.to_owned(),
+            member: value["member"].as_str().map(|m| m.to_owned()),
+            ref_id: value["ref_id"].as_str()?.to_owned(),
+            text: value["text"].as_str()?.to_owned(),
+        })
+    }
+}
+
+/// Collects broken links while the pages are rendered in parallel.
+#[derive(Default)]
+pub struct BrokenLinks(Mutex<Vec<BrokenLink>>);
+
+impl BrokenLinks {
+    pub fn add(&self, link: BrokenLink) {
+        self.0.lock().unwrap().push(link);
+    }
+
+    /// Sorted by page, member and ref_id, so reports are stable between runs.
+    pub fn into_sorted(self) -> Vec<BrokenLink> {
+        let mut links = self.0.into_inner().unwrap();
+        links.sort_by(|a, b| {
+            (&a.page, &a.member, &a.ref_id, &a.text).cmp(&(&b.page, &b.member, &b.ref_id, &b.text))
+        });
+        links
+    }
+}
+
+/// Prints the links grouped by page.
+pub fn print_report(links: &[BrokenLink]) {
+    let mut by_page = BTreeMap::<&str, Vec<&BrokenLink>>::new();
+    for link in links {
+        by_page.entry(&link.page).or_default().push(link);
+    }
+    println!(
+        "WARNING: {} broken links on {} pages",
+        links.len(),
+        by_page.len()
+    );
+    for (page, links) in by_page {
+        println!("  {} ({})", page, links[0].source);
+        for link in links {
+            match &link.member {
+                Some(member) => println!("    {}: {} -> refid {}", member, link.text, link.ref_id),
+                None => println!("    {} -> refid {}", link.text, link.ref_id),
+            }
+        }
+    }
+}
+
+pub fn write_json(file_name: &Path, links: &[BrokenLink]) -> std::io::Result<()> {
+    let mut array = json::array![];
+    for link in links {
+        array.push(link.to_json()).unwrap();
+    }
+    std::fs::write(file_name, array.pretty(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(page: &str, member: Option<&str>, ref_id: &str) -> BrokenLink {
+        BrokenLink {
+            page: page.to_owned(),
+            source: format!("{}.h", page),
+            member: member.map(|m| m.to_owned()),
+            ref_id: ref_id.to_owned(),
+            text: "text".to_owned(),
+        }
+    }
+
+    #[test]
+    fn links_are_sorted_by_page_member_and_ref() {
+        let links = BrokenLinks::default();
+        links.add(link("b", None, "r1"));
+        links.add(link("a", Some("f"), "r2"));
+        links.add(link("a", Some("f"), "r1"));
+        links.add(link("a", None, "r3"));
+        let sorted = links
+            .into_sorted()
+            .into_iter()
+            .map(|l| (l.page, l.member, l.ref_id))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("a", None, "r3"),
+            ("a", Some("f"), "r1"),
+            ("a", Some("f"), "r2"),
+            ("b", None, "r1"),
+        ]
+        .iter()
+        .map(|(p, m, r)| (p.to_string(), m.map(|m| m.to_string()), r.to_string()))
+        .collect::<Vec<_>>();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        for original in &[link("a", Some("ns::f"), "r"), link("a", None, "r")] {
+            let json = original.to_json();
+            let parsed = BrokenLink::from_json(&json::parse(&json.dump()).unwrap()).unwrap();
+            assert_eq!(parsed.page, original.page);
+            assert_eq!(parsed.source, original.source);
+            assert_eq!(parsed.member, original.member);
+            assert_eq!(parsed.ref_id, original.ref_id);
+            assert_eq!(parsed.text, original.text);
+        }
+        assert!(BrokenLink::from_json(&json::object! { page: "a" }).is_none());
+    }
+
+    #[test]
+    fn json_report_is_an_array() {
+        let file_name = crate::test_dir("broken-links").join("broken.json");
+        write_json(&file_name, &[link("a", None, "r")]).unwrap();
+        let report = json::parse(&std::fs::read_to_string(&file_name).unwrap()).unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0]["ref_id"], "r");
+        assert!(report[0]["member"].is_null());
+    }
+}
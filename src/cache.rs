@@ -0,0 +1,240 @@
+use crate::broken_links::BrokenLink;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+// The build cache lets a run skip compounds whose pages would come out the same as last time.
+// It is a JSON manifest in the output directory:
+//
+// {
+//   "version": 1,
+//   "settings": hash of the templates, static files, project info and parse options,
+//   "refs": { ref_id: path, and the hash of the link tooltip if it has one },
+//   "compounds": {
+//     ref_id: {
+//       "inputs": { XML file: content hash },
+//       "links": [ referenced ref_id, ... ],
+//       "broken_links": [ ... ]   // reported again when the page is skipped
+//     }
+//   }
+// }
+//
+// A compound is up to date if the settings and its inputs are unchanged, and every ref it links
+// to still resolves to the same path and tooltip. A missing or unreadable manifest just means a
+// full build.
+//
+// Only rendering is incremental. Every run still parses all the XML, since the link targets,
+// tooltips, navigation and search indexes are built from every compound.
+
+pub const FILE_NAME: &str = ".doxyrs-cache.json";
+const VERSION: u32 = 1;
+
+#[derive(Default)]
+pub struct Manifest {
+    pub settings: String,
+    pub refs: HashMap<String, String>,
+    pub compounds: BTreeMap<String, CompoundEntry>,
+}
+
+pub struct CompoundEntry {
+    pub inputs: BTreeMap<String, String>,
+    pub links: BTreeSet<String>,
+    pub broken_links: Vec<BrokenLink>,
+}
+
+impl Manifest {
+    pub fn load(html_dir: &Path) -> Manifest {
+        let content = match std::fs::read_to_string(html_dir.join(FILE_NAME)) {
+            Ok(content) => content,
+            Err(_) => return Manifest::default(),
+        };
+        match json::parse(&content) {
+            Ok(value) if value["version"].as_u32() == Some(VERSION) => from_json(&value),
+            _ => Manifest::default(),
+        }
+    }
+
+    pub fn save(&self, html_dir: &Path) -> std::io::Result<()> {
+        let mut refs = json::object! {};
+        let mut sorted_refs: Vec<_> = self.refs.iter().collect();
+        sorted_refs.sort();
+        for (ref_id, path) in sorted_refs {
+            refs[ref_id.as_str()] = path.as_str().into();
+        }
+
+        let mut compounds = json::object! {};
+        for (ref_id, entry) in &self.compounds {
+            let mut inputs = json::object! {};
+            for (file, hash) in &entry.inputs {
+                inputs[file.as_str()] = hash.as_str().into();
+            }
+            let mut broken_links = json::array![];
+            for link in &entry.broken_links {
+                broken_links.push(link.to_json()).unwrap();
+            }
+            compounds[ref_id.as_str()] = json::object! {
+                inputs: inputs,
+                links: entry.links.iter().map(|l| l.as_str()).collect::<Vec<_>>(),
+                broken_links: broken_links,
+            };
+        }
+
+        let manifest = json::object! {
+            version: VERSION,
+            settings: self.settings.as_str(),
+            refs: refs,
+            compounds: compounds,
+        };
+        std::fs::write(html_dir.join(FILE_NAME), manifest.dump())
+    }
+
+    /// Whether the page of a compound with these inputs and links would be unchanged.
+    pub fn is_up_to_date(
+        &self,
+        ref_id: &str,
+        inputs: &BTreeMap<String, String>,
+        refs: &HashMap<String, String>,
+    ) -> bool {
+        match self.compounds.get(ref_id) {
+            Some(entry) => {
+                entry.inputs == *inputs
+                    && entry
+                        .links
+                        .iter()
+                        .all(|link| self.refs.get(link) == refs.get(link))
+            }
+            None => false,
+        }
+    }
+}
+
+fn from_json(value: &json::JsonValue) -> Manifest {
+    let mut manifest = Manifest {
+        settings: value["settings"].as_str().unwrap_or_default().to_owned(),
+        ..Manifest::default()
+    };
+    for (ref_id, path) in value["refs"].entries() {
+        if let Some(path) = path.as_str() {
+            manifest.refs.insert(ref_id.to_owned(), path.to_owned());
+        }
+    }
+    for (ref_id, entry) in value["compounds"].entries() {
+        let inputs = entry["inputs"]
+            .entries()
+            .filter_map(|(file, hash)| Some((file.to_owned(), hash.as_str()?.to_owned())))
+            .collect();
+        let links = entry["links"]
+            .members()
+            .filter_map(|link| link.as_str().map(|l| l.to_owned()))
+            .collect();
+        let broken_links = entry["broken_links"]
+            .members()
+            .filter_map(BrokenLink::from_json)
+            .collect();
+        manifest.compounds.insert(
+            ref_id.to_owned(),
+            CompoundEntry {
+                inputs,
+                links,
+                broken_links,
+            },
+        );
+    }
+    manifest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map<T: std::iter::FromIterator<(String, String)>>(pairs: &[(&str, &str)]) -> T {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// A manifest with one compound `a` that was parsed from `a.xml` and links to `b`.
+    fn manifest() -> Manifest {
+        let mut manifest = Manifest {
+            settings: "settings".to_owned(),
+            refs: map(&[("b", "b.html 1234"), ("c", "c.html")]),
+            compounds: BTreeMap::new(),
+        };
+        manifest.compounds.insert(
+            "a".to_owned(),
+            CompoundEntry {
+                inputs: map(&[("a.xml", "5678")]),
+                links: ["b".to_owned()].iter().cloned().collect(),
+                broken_links: vec![],
+            },
+        );
+        manifest
+    }
+
+    #[test]
+    fn compounds_are_up_to_date_until_inputs_or_linked_refs_change() {
+        let manifest = manifest();
+        let inputs = map(&[("a.xml", "5678")]);
+        let refs = manifest.refs.clone();
+        assert!(manifest.is_up_to_date("a", &inputs, &refs));
+        assert!(!manifest.is_up_to_date("new", &inputs, &refs));
+        assert!(!manifest.is_up_to_date("a", &map(&[("a.xml", "0000")]), &refs));
+        let more_inputs = map(&[("a.xml", "5678"), ("image:pic.png", "")]);
+        assert!(!manifest.is_up_to_date("a", &more_inputs, &refs));
+
+        // a moved page or a new tooltip of a linked ref changes the link
+        for changed in &[("b", "other.html 1234"), ("b", "b.html 4321")] {
+            let mut refs = refs.clone();
+            refs.insert(changed.0.to_owned(), changed.1.to_owned());
+            assert!(!manifest.is_up_to_date("a", &inputs, &refs));
+        }
+        let mut refs_without_b = refs.clone();
+        refs_without_b.remove("b");
+        assert!(!manifest.is_up_to_date("a", &inputs, &refs_without_b));
+
+        // refs the page does not link to don't matter
+        let mut other_refs = refs.clone();
+        other_refs.insert("c".to_owned(), "elsewhere.html".to_owned());
+        assert!(manifest.is_up_to_date("a", &inputs, &other_refs));
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let html_dir = crate::test_dir("cache");
+        let mut manifest = manifest();
+        manifest.compounds.get_mut("a").unwrap().broken_links = vec![BrokenLink {
+            page: "a.html".to_owned(),
+            source: "a.h".to_owned(),
+            member: None,
+            ref_id: "missing".to_owned(),
+            text: "gone".to_owned(),
+        }];
+        manifest.save(&html_dir).unwrap();
+
+        let loaded = Manifest::load(&html_dir);
+        assert_eq!(loaded.settings, manifest.settings);
+        assert_eq!(loaded.refs, manifest.refs);
+        let entry = &loaded.compounds["a"];
+        assert_eq!(entry.inputs, manifest.compounds["a"].inputs);
+        assert_eq!(entry.links, manifest.compounds["a"].links);
+        assert_eq!(entry.broken_links.len(), 1);
+        assert_eq!(entry.broken_links[0].ref_id, "missing");
+    }
+
+    #[test]
+    fn missing_or_outdated_manifests_are_empty() {
+        let html_dir = crate::test_dir("cache-outdated");
+        assert!(Manifest::load(&html_dir).compounds.is_empty());
+
+        manifest().save(&html_dir).unwrap();
+        let path = html_dir.join(FILE_NAME);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut value = json::parse(&content).unwrap();
+        value["version"] = (VERSION + 1).into();
+        std::fs::write(&path, value.dump()).unwrap();
+        assert!(Manifest::load(&html_dir).compounds.is_empty());
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(Manifest::load(&html_dir).compounds.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::error::DoxyError;
+use crate::math::MathBackend;
+use crate::parser::Protection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// doxyrs.toml. All keys are optional; command line flags take precedence.
+//
+// [project]
+// name = "My Library"
+// version = "1.2.0"
+// logo = "docs/logo.svg"
+// footer = "© 2024 Someone"
+//
+// [input]
+// source = ".."
+// xml = "../build/doxygen/xml"
+//
+// [output]
+// dir = "../build/html"
+// layout = "per-scope"
+// member_layout = "summary"
+// theme_dir = "doc-theme"
+// math = "mathml"
+//
+// [filter]
+// include_protection = ["public", "protected"]
+//
+// [check]
+// fail_on_broken_links = true
+// broken_links_json = "../build/broken-links.json"
+
+pub const DEFAULT_FILE_NAME: &str = "doxyrs.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub project: Project,
+    pub input: Input,
+    pub output: Output,
+    pub filter: Filter,
+    pub check: Check,
+}
+
+/// Passed to the templates as `project`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Project {
+    pub name: String,
+    pub version: Option<String>,
+    /// Path to an image file. The templates see its URL relative to the output directory.
+    pub logo: Option<String>,
+    pub footer: Option<String>,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Project {
+            name: "Documentation".to_owned(),
+            version: None,
+            logo: None,
+            footer: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Input {
+    pub source: Option<PathBuf>,
+    pub xml: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Output {
+    pub dir: Option<PathBuf>,
+    pub layout: Option<Layout>,
+    pub member_layout: Option<MemberLayout>,
+    /// See theme.rs for the layout of a theme directory.
+    pub theme_dir: Option<PathBuf>,
+    pub math: Option<MathBackend>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Filter {
+    pub include_protection: Option<Vec<Protection>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Check {
+    pub fail_on_broken_links: bool,
+    pub broken_links_json: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    PerFile,
+    PerScope,
+}
+
+/// Passed to the templates as `member_layout`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MemberLayout {
+    /// Each member's definition and description, one after another
+    #[default]
+    Detailed,
+    /// A table of all members with their brief descriptions, then the detailed block
+    Summary,
+}
+
+impl Config {
+    /// Reads a config file. Relative paths in it are resolved against its directory, not the
+    /// working directory.
+    pub fn load(file_name: &Path) -> Result<Config, DoxyError> {
+        let content =
+            std::fs::read_to_string(file_name).map_err(|e| DoxyError::io(file_name, e))?;
+        let mut config: Config = toml::from_str(&content).map_err(|source| DoxyError::Config {
+            file: file_name.to_owned(),
+            source,
+        })?;
+
+        let base_dir = file_name.parent().unwrap_or_else(|| Path::new(""));
+        let resolve = |path: &mut Option<PathBuf>| {
+            if let Some(path) = path {
+                *path = base_dir.join(&*path);
+            }
+        };
+        resolve(&mut config.input.source);
+        resolve(&mut config.input.xml);
+        resolve(&mut config.output.dir);
+        resolve(&mut config.output.theme_dir);
+        resolve(&mut config.check.broken_links_json);
+        if let Some(logo) = &mut config.project.logo {
+            *logo = base_dir.join(&*logo).to_string_lossy().into_owned();
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_relative_to_the_config_file() {
+        let dir = crate::test_dir("config-paths");
+        let file_name = dir.join("doxyrs.toml");
+        std::fs::write(
+            &file_name,
+            "[project]\nname = \"Lib\"\nlogo = \"docs/logo.svg\"\n\
+             [input]\nsource = \"..\"\nxml = \"build/xml\"\n\
+             [output]\ndir = \"/abs/html\"\nlayout = \"per-scope\"\nmember_layout = \"summary\"\n\
+             [filter]\ninclude_protection = [\"public\", \"protected\"]\n\
+             [check]\nfail_on_broken_links = true\n",
+        )
+        .unwrap();
+        let config = Config::load(&file_name).unwrap();
+        assert_eq!(config.project.name, "Lib");
+        assert_eq!(
+            config.project.logo,
+            Some(dir.join("docs/logo.svg").to_string_lossy().into_owned())
+        );
+        assert_eq!(config.input.source, Some(dir.join("..")));
+        assert_eq!(config.input.xml, Some(dir.join("build/xml")));
+        assert_eq!(config.output.dir, Some(PathBuf::from("/abs/html")));
+        assert_eq!(config.output.layout, Some(Layout::PerScope));
+        assert_eq!(config.output.member_layout, Some(MemberLayout::Summary));
+        assert_eq!(
+            config.filter.include_protection,
+            Some(vec![Protection::Public, Protection::Protected])
+        );
+        assert!(config.check.fail_on_broken_links);
+        assert_eq!(config.output.theme_dir, None);
+    }
+
+    #[test]
+    fn missing_keys_get_defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.project.name, "Documentation");
+        assert!(!config.check.fail_on_broken_links);
+        assert_eq!(config.input.xml, None);
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        let dir = crate::test_dir("config-unknown");
+        let file_name = dir.join("doxyrs.toml");
+        std::fs::write(&file_name, "[output]\ndirectory = \"html\"\n").unwrap();
+        let error = Config::load(&file_name).err().unwrap();
+        assert!(matches!(error, DoxyError::Config { file, .. } if file == file_name));
+    }
+}
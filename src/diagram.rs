@@ -0,0 +1,301 @@
+// Inheritance diagrams as inline SVG, so no graphviz install is needed. Classes are laid out in
+// rows, base classes above the classes derived from them, each row centered. Edges point from a
+// derived class to its base, dashed for virtual inheritance.
+
+const FONT_SIZE: usize = 12;
+/// Rough width of a character, the browser measures the real text
+const CHAR_WIDTH: usize = 7;
+const BOX_HEIGHT: usize = 26;
+const BOX_PADDING: usize = 10;
+const ROW_GAP: usize = 40;
+const COLUMN_GAP: usize = 20;
+const MARGIN: usize = 4;
+
+pub struct Node {
+    pub label: String,
+    /// Classes without documentation are not linked
+    pub ref_id: Option<String>,
+    /// The class the diagram is about
+    pub is_current: bool,
+}
+
+pub struct Edge {
+    pub derived: usize,
+    pub base: usize,
+    pub protection: String,
+    pub is_virtual: bool,
+}
+
+#[derive(Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Index of the node for a class, added if it is new. Classes are told apart by `ref_id`, so
+    /// classes with the same name in different namespaces get boxes of their own. Only
+    /// undocumented classes, which have none, are told apart by `label`.
+    pub fn node(&mut self, label: &str, ref_id: Option<&str>) -> usize {
+        let existing = match ref_id {
+            Some(ref_id) => self
+                .nodes
+                .iter()
+                .position(|n| n.ref_id.as_deref() == Some(ref_id)),
+            None => self
+                .nodes
+                .iter()
+                .position(|n| n.ref_id.is_none() && n.label == label),
+        };
+        match existing {
+            Some(index) => index,
+            None => {
+                self.nodes.push(Node {
+                    label: label.to_owned(),
+                    ref_id: ref_id.map(|r| r.to_owned()),
+                    is_current: false,
+                });
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// The nodes connected to `root` through edges, in either direction, and their edges.
+    pub fn connected_to(self, root: usize) -> Graph {
+        let mut is_connected = vec![false; self.nodes.len()];
+        is_connected[root] = true;
+        let mut pending = vec![root];
+        while let Some(index) = pending.pop() {
+            for edge in &self.edges {
+                for (from, to) in [(edge.derived, edge.base), (edge.base, edge.derived)] {
+                    if from == index && !is_connected[to] {
+                        is_connected[to] = true;
+                        pending.push(to);
+                    }
+                }
+            }
+        }
+
+        let mut new_index = vec![None; self.nodes.len()];
+        let mut nodes = vec![];
+        for (index, node) in self.nodes.into_iter().enumerate() {
+            if is_connected[index] {
+                new_index[index] = Some(nodes.len());
+                nodes.push(node);
+            }
+        }
+        let edges = self
+            .edges
+            .into_iter()
+            .filter_map(|edge| {
+                Some(Edge {
+                    derived: new_index[edge.derived]?,
+                    base: new_index[edge.base]?,
+                    ..edge
+                })
+            })
+            .collect();
+        Graph { nodes, edges }
+    }
+}
+
+/// The row of each node: a class sits one row below its lowest base. Cycles can't happen in
+/// valid C++; should the XML have one anyway, rows stop growing after one pass per node.
+fn assign_rows(graph: &Graph) -> Vec<usize> {
+    let mut rows = vec![0usize; graph.nodes.len()];
+    for _ in 0..graph.nodes.len() {
+        let mut changed = false;
+        for edge in &graph.edges {
+            if rows[edge.derived] <= rows[edge.base] {
+                rows[edge.derived] = rows[edge.base] + 1;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    rows
+}
+
+fn box_width(node: &Node) -> usize {
+    node.label.chars().count() * CHAR_WIDTH + 2 * BOX_PADDING
+}
+
+/// The top left corner of each node's box, each row centered, nodes in graph order within their
+/// row, and the width and height of the whole diagram.
+fn layout(graph: &Graph) -> (Vec<(usize, usize)>, usize, usize) {
+    let rows = assign_rows(graph);
+    let n_rows = rows.iter().max().map_or(0, |r| r + 1);
+
+    let row_width = |row: usize| {
+        let widths: Vec<usize> = (0..graph.nodes.len())
+            .filter(|&i| rows[i] == row)
+            .map(|i| box_width(&graph.nodes[i]))
+            .collect();
+        widths.iter().sum::<usize>() + COLUMN_GAP * widths.len().saturating_sub(1)
+    };
+    let width = (0..n_rows).map(row_width).max().unwrap_or(0) + 2 * MARGIN;
+    let height = n_rows * BOX_HEIGHT + n_rows.saturating_sub(1) * ROW_GAP + 2 * MARGIN;
+
+    let mut positions = vec![(0, 0); graph.nodes.len()];
+    for row in 0..n_rows {
+        let mut x = (width - row_width(row)) / 2;
+        for i in (0..graph.nodes.len()).filter(|&i| rows[i] == row) {
+            positions[i] = (x, MARGIN + row * (BOX_HEIGHT + ROW_GAP));
+            x += box_width(&graph.nodes[i]) + COLUMN_GAP;
+        }
+    }
+    (positions, width, height)
+}
+
+/// `id` makes the IDs in the SVG unique when a page has several diagrams.
+pub fn render_svg(graph: &Graph, id: &str) -> String {
+    let (positions, width, height) = layout(graph);
+
+    let mut svg = format!(
+        "<svg class=\"inheritance-diagram\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+        width, height
+    );
+    let marker_id = format!("inheritance-arrow-{}", id);
+    svg.push_str(&format!(
+        "<defs><marker id=\"{}\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+         markerWidth=\"10\" markerHeight=\"10\" orient=\"auto-start-reverse\">\
+         <path d=\"M 0 0 L 10 5 L 0 10 z\" class=\"diagram-arrow\"/></marker></defs>",
+        marker_id
+    ));
+    for edge in &graph.edges {
+        let (dx, dy) = positions[edge.derived];
+        let (bx, by) = positions[edge.base];
+        svg.push_str(&format!(
+            "<line class=\"diagram-edge diagram-edge-{}\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"{} marker-end=\"url(#{})\"/>",
+            edge.protection,
+            dx + box_width(&graph.nodes[edge.derived]) / 2,
+            dy,
+            bx + box_width(&graph.nodes[edge.base]) / 2,
+            by + BOX_HEIGHT,
+            if edge.is_virtual {
+                " stroke-dasharray=\"4 3\""
+            } else {
+                ""
+            },
+            marker_id
+        ));
+    }
+    for (node, &(x, y)) in graph.nodes.iter().zip(&positions) {
+        let shape = format!(
+            "<rect class=\"diagram-node{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"3\"/>\
+             <text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+            if node.is_current {
+                " diagram-current"
+            } else {
+                ""
+            },
+            x,
+            y,
+            box_width(node),
+            BOX_HEIGHT,
+            x + box_width(node) / 2,
+            y + BOX_HEIGHT / 2,
+            FONT_SIZE,
+            tera::escape_html(&node.label)
+        );
+        match &node.ref_id {
+            Some(ref_id) if !node.is_current => {
+                svg.push_str(&format!("<a href=\"refid://{}\">{}</a>", ref_id, shape))
+            }
+            _ => svg.push_str(&shape),
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A graph of documented classes, named by their ref ids.
+    fn graph(labels: &[&str], edges: &[(usize, usize)]) -> Graph {
+        let mut graph = Graph::default();
+        for label in labels {
+            graph.node(label, Some(label));
+        }
+        for &(derived, base) in edges {
+            graph.edges.push(Edge {
+                derived,
+                base,
+                protection: "public".to_owned(),
+                is_virtual: false,
+            });
+        }
+        graph
+    }
+
+    #[test]
+    fn nodes_are_told_apart_by_ref_id() {
+        let mut graph = Graph::default();
+        let a = graph.node("Base", Some("classa_1_1Base"));
+        let b = graph.node("Base", Some("classb_1_1Base"));
+        assert_ne!(a, b);
+        assert_eq!(graph.node("a::Base", Some("classa_1_1Base")), a);
+        let std1 = graph.node("std::string", None);
+        assert_eq!(graph.node("std::string", None), std1);
+        assert_eq!(graph.nodes.len(), 3);
+    }
+
+    #[test]
+    fn classes_sit_below_their_lowest_base() {
+        // a diamond with an extra level on one side: D -> B -> A, D -> C -> X -> A
+        let graph = graph(
+            &["A", "B", "C", "D", "X"],
+            &[(1, 0), (2, 4), (4, 0), (3, 1), (3, 2)],
+        );
+        assert_eq!(assign_rows(&graph), [0, 1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn rows_stop_growing_on_cycles() {
+        let graph = graph(&["A", "B"], &[(0, 1), (1, 0)]);
+        // one pass per node, each raising a node at most once per edge
+        assert!(assign_rows(&graph).iter().all(|&row| row <= 2 * 2));
+    }
+
+    #[test]
+    fn rows_are_centered() {
+        // one base over two derived classes
+        let graph = graph(&["Base", "L", "R"], &[(1, 0), (2, 0)]);
+        let (positions, width, height) = layout(&graph);
+        let small = box_width(&graph.nodes[1]);
+        assert_eq!(width, 2 * small + COLUMN_GAP + 2 * MARGIN);
+        assert_eq!(height, 2 * BOX_HEIGHT + ROW_GAP + 2 * MARGIN);
+        assert_eq!(positions[1], (MARGIN, MARGIN + BOX_HEIGHT + ROW_GAP));
+        assert_eq!(positions[2].0, MARGIN + small + COLUMN_GAP);
+        assert_eq!(
+            positions[0],
+            ((width - box_width(&graph.nodes[0])) / 2, MARGIN)
+        );
+    }
+
+    #[test]
+    fn connected_to_drops_unrelated_classes() {
+        let graph = graph(&["A", "B", "C", "D"], &[(1, 0), (3, 2)]).connected_to(1);
+        let labels: Vec<&str> = graph.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(labels, ["A", "B"]);
+        assert_eq!((graph.edges[0].derived, graph.edges[0].base), (1, 0));
+        assert_eq!(graph.edges.len(), 1);
+    }
+
+    #[test]
+    fn svg_uses_the_diagram_id_and_links_other_classes() {
+        let mut graph = graph(&["A", "B"], &[(1, 0)]);
+        graph.nodes[1].is_current = true;
+        graph.edges[0].is_virtual = true;
+        let svg = render_svg(&graph, "classB");
+        assert!(svg.contains("<marker id=\"inheritance-arrow-classB\""));
+        assert!(svg.contains("marker-end=\"url(#inheritance-arrow-classB)\""));
+        assert!(svg.contains("stroke-dasharray"));
+        assert!(svg.contains("<a href=\"refid://A\">"));
+        assert!(!svg.contains("refid://B"));
+    }
+}
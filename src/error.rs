@@ -0,0 +1,206 @@
+use roxmltree::{Node, TextPos};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum DoxyError {
+    Io {
+        file: PathBuf,
+        source: std::io::Error,
+    },
+    Xml {
+        file: PathBuf,
+        source: roxmltree::Error,
+    },
+    MissingElement {
+        file: PathBuf,
+        pos: TextPos,
+        parent: String,
+        name: String,
+    },
+    MissingAttribute {
+        file: PathBuf,
+        pos: TextPos,
+        element: String,
+        name: String,
+    },
+    UnsupportedKind {
+        file: PathBuf,
+        pos: TextPos,
+        element: String,
+        kind: String,
+    },
+    Render {
+        file: PathBuf,
+        source: tera::Error,
+    },
+    Config {
+        file: PathBuf,
+        source: toml::de::Error,
+    },
+    /// Reported as a warning, the formula is shown as TeX source
+    InvalidFormula {
+        file: PathBuf,
+        pos: TextPos,
+        formula: String,
+        message: String,
+    },
+}
+
+impl DoxyError {
+    pub fn io(file: &Path, source: std::io::Error) -> Self {
+        DoxyError::Io {
+            file: file.to_owned(),
+            source,
+        }
+    }
+
+    pub fn missing_element(parent: Node, name: &str) -> Self {
+        DoxyError::MissingElement {
+            file: PathBuf::new(),
+            pos: position(parent),
+            parent: parent.tag_name().name().to_owned(),
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn missing_attribute(element: Node, name: &str) -> Self {
+        DoxyError::MissingAttribute {
+            file: PathBuf::new(),
+            pos: position(element),
+            element: element.tag_name().name().to_owned(),
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn unsupported_kind(element: Node, kind: &str) -> Self {
+        DoxyError::UnsupportedKind {
+            file: PathBuf::new(),
+            pos: position(element),
+            element: element.tag_name().name().to_owned(),
+            kind: kind.to_owned(),
+        }
+    }
+
+    pub fn invalid_formula(element: Node, formula: &str, message: String) -> Self {
+        DoxyError::InvalidFormula {
+            file: PathBuf::new(),
+            pos: position(element),
+            formula: formula.to_owned(),
+            message,
+        }
+    }
+
+    /// Errors raised while walking a document don't know which file they came from.
+    /// The function that loaded the document fills it in on the way out.
+    pub fn in_file(mut self, file_name: &Path) -> Self {
+        match &mut self {
+            DoxyError::MissingElement { file, .. }
+            | DoxyError::MissingAttribute { file, .. }
+            | DoxyError::UnsupportedKind { file, .. }
+            | DoxyError::InvalidFormula { file, .. } => {
+                if file.as_os_str().is_empty() {
+                    *file = file_name.to_owned();
+                }
+            }
+            DoxyError::Io { .. }
+            | DoxyError::Xml { .. }
+            | DoxyError::Render { .. }
+            | DoxyError::Config { .. } => {}
+        }
+        self
+    }
+}
+
+fn position(node: Node) -> TextPos {
+    node.document().text_pos_at(node.range().start)
+}
+
+impl fmt::Display for DoxyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DoxyError::Io { file, source } => {
+                write!(f, "{}: {}", file.display(), source)
+            }
+            DoxyError::Xml { file, source } => {
+                write!(f, "{}: XML error: {}", file.display(), source)
+            }
+            DoxyError::MissingElement {
+                file,
+                pos,
+                parent,
+                name,
+            } => write!(
+                f,
+                "{}:{}: <{}> has no (unique) <{}> child",
+                file.display(),
+                pos,
+                parent,
+                name
+            ),
+            DoxyError::MissingAttribute {
+                file,
+                pos,
+                element,
+                name,
+            } => write!(
+                f,
+                "{}:{}: <{}> has no '{}' attribute",
+                file.display(),
+                pos,
+                element,
+                name
+            ),
+            DoxyError::UnsupportedKind {
+                file,
+                pos,
+                element,
+                kind,
+            } => write!(
+                f,
+                "{}:{}: <{}> of kind '{}' is not supported",
+                file.display(),
+                pos,
+                element,
+                kind
+            ),
+            DoxyError::Render { file, source } => {
+                write!(f, "{}: rendering failed: {}", file.display(), source)?;
+                let mut cause = std::error::Error::source(source);
+                while let Some(c) = cause {
+                    write!(f, ": {}", c)?;
+                    cause = c.source();
+                }
+                Ok(())
+            }
+            DoxyError::Config { file, source } => {
+                write!(f, "{}: {}", file.display(), source)
+            }
+            DoxyError::InvalidFormula {
+                file,
+                pos,
+                formula,
+                message,
+            } => write!(
+                f,
+                "{}:{}: formula {:?} could not be converted: {}",
+                file.display(),
+                pos,
+                formula,
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DoxyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DoxyError::Io { source, .. } => Some(source),
+            DoxyError::Xml { source, .. } => Some(source),
+            DoxyError::Render { source, .. } => Some(source),
+            DoxyError::Config { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
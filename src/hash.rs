@@ -0,0 +1,44 @@
+/// 64-bit FNV-1a as 16 hex digits. Stable across builds and platforms, unlike `DefaultHasher`,
+/// which matters for file names that browsers and the build cache remember.
+pub fn content_hash(data: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in data {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// `name.ext` -> `name.<hash>.ext`
+pub fn hashed_file_name(name: &str, data: &[u8]) -> String {
+    with_hash(name, &content_hash(data))
+}
+
+/// `name.ext` -> `name.<hash>.ext`, for a hash that is already known
+pub fn with_hash(name: &str, hash: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}.{}.{}", stem, hash, extension),
+        None => format!("{}.{}", name, hash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_are_stable() {
+        // FNV-1a test vectors
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+        assert_ne!(content_hash(b"ab"), content_hash(b"ba"));
+    }
+
+    #[test]
+    fn hash_goes_before_the_extension() {
+        assert_eq!(with_hash("style.css", "0123"), "style.0123.css");
+        assert_eq!(with_hash("jquery.min.js", "0123"), "jquery.min.0123.js");
+        assert_eq!(with_hash("LICENSE", "0123"), "LICENSE.0123");
+        assert_eq!(hashed_file_name("a.png", b"a"), "a.af63dc4c8601ec8c.png");
+    }
+}
@@ -0,0 +1,642 @@
+use std::ops::Range;
+
+// A small syntax highlighter for code blocks whose language is known from their file name, and
+// for verbatim blocks. Doxygen only highlights the languages it parses, and coarsely. This knows
+// comments, string and character literals, preprocessor lines and keywords, which is what the
+// `highlight-*` classes of doxygen's own output distinguish, so themes style both the same way.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Normal,
+    Keyword,
+    KeywordFlow,
+    KeywordType,
+    Comment,
+    StringLiteral,
+    CharLiteral,
+    Preprocessor,
+}
+
+impl Class {
+    /// The CSS class, as used by doxygen's `<highlight class="...">`.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Class::Normal => "highlight-normal",
+            Class::Keyword => "highlight-keyword",
+            Class::KeywordFlow => "highlight-keywordflow",
+            Class::KeywordType => "highlight-keywordtype",
+            Class::Comment => "highlight-comment",
+            Class::StringLiteral => "highlight-stringliteral",
+            Class::CharLiteral => "highlight-charliteral",
+            Class::Preprocessor => "highlight-preprocessor",
+        }
+    }
+}
+
+pub struct Language {
+    keywords: &'static [&'static str],
+    flow_keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    case_sensitive: bool,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    string_quotes: &'static [char],
+    /// Quote of character literals, if it differs from string quotes
+    char_quote: Option<char>,
+    /// Python's `"""` and `'''`
+    triple_quotes: bool,
+    /// Lines starting with `#` are preprocessor directives
+    preprocessor: bool,
+}
+
+pub const CPP: Language = Language {
+    keywords: &[
+        "alignas",
+        "alignof",
+        "asm",
+        "class",
+        "concept",
+        "const",
+        "consteval",
+        "constexpr",
+        "constinit",
+        "const_cast",
+        "decltype",
+        "delete",
+        "dynamic_cast",
+        "enum",
+        "explicit",
+        "export",
+        "extern",
+        "final",
+        "friend",
+        "inline",
+        "mutable",
+        "namespace",
+        "new",
+        "noexcept",
+        "nullptr",
+        "operator",
+        "override",
+        "private",
+        "protected",
+        "public",
+        "register",
+        "reinterpret_cast",
+        "requires",
+        "sizeof",
+        "static",
+        "static_assert",
+        "static_cast",
+        "struct",
+        "template",
+        "this",
+        "thread_local",
+        "typedef",
+        "typeid",
+        "typename",
+        "union",
+        "using",
+        "virtual",
+        "volatile",
+        "true",
+        "false",
+    ],
+    flow_keywords: &[
+        "break",
+        "case",
+        "catch",
+        "continue",
+        "co_await",
+        "co_return",
+        "co_yield",
+        "default",
+        "do",
+        "else",
+        "for",
+        "goto",
+        "if",
+        "return",
+        "switch",
+        "throw",
+        "try",
+        "while",
+    ],
+    types: &[
+        "auto", "bool", "char", "char8_t", "char16_t", "char32_t", "double", "float", "int",
+        "long", "short", "signed", "unsigned", "void", "wchar_t", "size_t", "int8_t", "int16_t",
+        "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t",
+    ],
+    case_sensitive: true,
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    string_quotes: &['"', '\''],
+    char_quote: Some('\''),
+    triple_quotes: false,
+    preprocessor: true,
+};
+
+pub const PYTHON: Language = Language {
+    keywords: &[
+        "and", "as", "async", "await", "class", "def", "del", "global", "import", "from", "in",
+        "is", "lambda", "nonlocal", "not", "or", "None", "True", "False",
+    ],
+    flow_keywords: &[
+        "assert", "break", "continue", "elif", "else", "except", "finally", "for", "if", "pass",
+        "raise", "return", "try", "while", "with", "yield",
+    ],
+    types: &[
+        "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+    ],
+    case_sensitive: true,
+    line_comments: &["#"],
+    block_comment: None,
+    string_quotes: &['"', '\''],
+    char_quote: None,
+    triple_quotes: true,
+    preprocessor: false,
+};
+
+pub const CMAKE: Language = Language {
+    keywords: &[
+        "add_compile_definitions",
+        "add_compile_options",
+        "add_custom_command",
+        "add_custom_target",
+        "add_definitions",
+        "add_dependencies",
+        "add_executable",
+        "add_library",
+        "add_subdirectory",
+        "add_test",
+        "cmake_minimum_required",
+        "cmake_parse_arguments",
+        "cmake_policy",
+        "configure_file",
+        "enable_testing",
+        "find_library",
+        "find_package",
+        "find_path",
+        "find_program",
+        "get_filename_component",
+        "get_property",
+        "get_target_property",
+        "include",
+        "include_directories",
+        "install",
+        "link_directories",
+        "link_libraries",
+        "list",
+        "message",
+        "option",
+        "project",
+        "set",
+        "set_property",
+        "set_target_properties",
+        "string",
+        "target_compile_definitions",
+        "target_compile_features",
+        "target_compile_options",
+        "target_include_directories",
+        "target_link_libraries",
+        "target_link_options",
+        "target_sources",
+        "unset",
+    ],
+    flow_keywords: &[
+        "if",
+        "elseif",
+        "else",
+        "endif",
+        "foreach",
+        "endforeach",
+        "while",
+        "endwhile",
+        "function",
+        "endfunction",
+        "macro",
+        "endmacro",
+        "return",
+        "break",
+        "continue",
+        "block",
+        "endblock",
+    ],
+    types: &[
+        "PUBLIC",
+        "PRIVATE",
+        "INTERFACE",
+        "STATIC",
+        "SHARED",
+        "MODULE",
+        "OBJECT",
+        "IMPORTED",
+        "REQUIRED",
+        "COMPONENTS",
+        "CONFIG",
+        "VERSION",
+        "TARGETS",
+        "DESTINATION",
+        "CACHE",
+        "FORCE",
+        "ON",
+        "OFF",
+        "TRUE",
+        "FALSE",
+        "AND",
+        "OR",
+        "NOT",
+        "DEFINED",
+        "STREQUAL",
+        "EQUAL",
+        "LESS",
+        "GREATER",
+        "MATCHES",
+        "EXISTS",
+    ],
+    case_sensitive: false,
+    line_comments: &["#"],
+    block_comment: None,
+    string_quotes: &['"'],
+    char_quote: None,
+    triple_quotes: false,
+    preprocessor: false,
+};
+
+pub const JSON: Language = Language {
+    keywords: &[],
+    flow_keywords: &[],
+    types: &["true", "false", "null"],
+    case_sensitive: true,
+    line_comments: &[],
+    block_comment: None,
+    string_quotes: &['"'],
+    char_quote: None,
+    triple_quotes: false,
+    preprocessor: false,
+};
+
+pub const SHELL: Language = Language {
+    keywords: &[
+        "alias", "cd", "echo", "exec", "export", "function", "local", "read", "readonly", "set",
+        "shift", "source", "unset",
+    ],
+    flow_keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "exit", "fi", "for", "if", "in", "return",
+        "then", "until", "while",
+    ],
+    types: &[],
+    case_sensitive: true,
+    line_comments: &["#"],
+    block_comment: None,
+    string_quotes: &['"', '\''],
+    char_quote: None,
+    triple_quotes: false,
+    preprocessor: false,
+};
+
+/// For blocks of unknown language: only strings and `#` comments. Not `//`, which shows up in
+/// URLs far more often than as a comment in text that isn't code.
+pub const GENERIC: Language = Language {
+    keywords: &[],
+    flow_keywords: &[],
+    types: &[],
+    case_sensitive: true,
+    line_comments: &["#"],
+    block_comment: None,
+    string_quotes: &['"'],
+    char_quote: None,
+    triple_quotes: false,
+    preprocessor: false,
+};
+
+/// Finds the language for a file name (`CMakeLists.txt`, `setup.py`), an extension as doxygen
+/// writes it for fenced code (`.py`) or a language name (`python`).
+pub fn language_for(name: &str) -> Option<&'static Language> {
+    let name = name.trim().to_lowercase();
+    if name == "cmakelists.txt" {
+        return Some(&CMAKE);
+    }
+    let extension = name.rsplit('.').next().unwrap_or_default();
+    Some(match extension {
+        "c" | "cc" | "cpp" | "cxx" | "c++" | "h" | "hh" | "hpp" | "hxx" | "ipp" | "inl" => &CPP,
+        "py" | "python" => &PYTHON,
+        "cmake" => &CMAKE,
+        "json" => &JSON,
+        "sh" | "bash" | "shell" | "zsh" | "console" => &SHELL,
+        _ => return None,
+    })
+}
+
+/// A `refid://` link over a range of the code.
+pub struct Link {
+    pub range: Range<usize>,
+    pub ref_id: String,
+}
+
+/// Highlights `code` as HTML for a `<pre>` block.
+pub fn highlight(code: &str, language: &Language) -> String {
+    highlight_with_links(code, language, &[])
+}
+
+/// Like `highlight`, keeping links to documented entities. Links that cross token boundaries are
+/// split.
+pub fn highlight_with_links(code: &str, language: &Language, links: &[Link]) -> String {
+    let mut html = String::new();
+    let mut pos = 0;
+    let write_token = |range: Range<usize>, class: Class, html: &mut String| {
+        if range.is_empty() {
+            return;
+        }
+        html.push_str(&format!("<span class=\"{}\">", class.css_class()));
+        let mut start = range.start;
+        for link in links {
+            let link_start = link.range.start.max(range.start);
+            let link_end = link.range.end.min(range.end);
+            if link_start >= link_end || link_start < start {
+                continue;
+            }
+            html.push_str(&tera::escape_html(&code[start..link_start]));
+            html.push_str(&format!(
+                "<a href=\"refid://{}\">{}</a>",
+                link.ref_id,
+                tera::escape_html(&code[link_start..link_end])
+            ));
+            start = link_end;
+        }
+        html.push_str(&tera::escape_html(&code[start..range.end]));
+        html.push_str("</span>");
+    };
+    for (range, class) in tokenize(code, language) {
+        write_token(pos..range.start, Class::Normal, &mut html);
+        pos = range.end;
+        write_token(range, class, &mut html);
+    }
+    write_token(pos..code.len(), Class::Normal, &mut html);
+    html
+}
+
+/// The ranges of `code` that are not `Class::Normal`, in order.
+fn tokenize(code: &str, language: &Language) -> Vec<(Range<usize>, Class)> {
+    let mut tokens = vec![];
+    let mut at_line_start = true;
+    let mut i = 0;
+    while i < code.len() {
+        let rest = &code[i..];
+        let c = rest.chars().next().unwrap();
+        let line_end = rest.find('\n').map_or(code.len(), |end| i + end);
+
+        if language.preprocessor && at_line_start && c == '#' {
+            tokens.push((i..line_end, Class::Preprocessor));
+            i = line_end;
+            continue;
+        }
+        // `#` only starts a comment at the beginning of a word, as in shell scripts
+        let at_word_start = code[..i]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        if language
+            .line_comments
+            .iter()
+            .any(|p| rest.starts_with(p) && (*p != "#" || at_word_start))
+        {
+            tokens.push((i..line_end, Class::Comment));
+            i = line_end;
+            continue;
+        }
+        if let Some((open, close)) = language.block_comment {
+            if let Some(after_open) = rest.strip_prefix(open) {
+                let end = after_open
+                    .find(close)
+                    .map_or(code.len(), |end| i + open.len() + end + close.len());
+                tokens.push((i..end, Class::Comment));
+                i = end;
+                at_line_start = false;
+                continue;
+            }
+        }
+        if language.triple_quotes && (rest.starts_with("\"\"\"") || rest.starts_with("'''")) {
+            let quotes = &rest[..3];
+            let end = rest[3..]
+                .find(quotes)
+                .map_or(code.len(), |end| i + 3 + end + 3);
+            tokens.push((i..end, Class::StringLiteral));
+            i = end;
+            at_line_start = false;
+            continue;
+        }
+        if language.string_quotes.contains(&c) {
+            let end = string_end(code, i, c).min(line_end);
+            let class = if language.char_quote == Some(c) {
+                Class::CharLiteral
+            } else {
+                Class::StringLiteral
+            };
+            tokens.push((i..end, class));
+            i = end;
+            at_line_start = false;
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(code.len(), |end| i + end);
+            if let Some(class) = keyword_class(&code[i..end], language) {
+                tokens.push((i..end, class));
+            }
+            i = end;
+            at_line_start = false;
+            continue;
+        }
+
+        if c == '\n' {
+            at_line_start = true;
+        } else if !c.is_whitespace() {
+            at_line_start = false;
+        }
+        i += c.len_utf8();
+    }
+    tokens
+}
+
+/// The end of the string literal starting with `quote` at `start`, after the closing quote.
+fn string_end(code: &str, start: usize, quote: char) -> usize {
+    let mut escaped = false;
+    for (offset, c) in code[start + 1..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return start + 1 + offset + 1,
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+fn keyword_class(word: &str, language: &Language) -> Option<Class> {
+    let matches = |list: &[&str]| {
+        list.iter().any(|k| {
+            if language.case_sensitive {
+                *k == word
+            } else {
+                k.eq_ignore_ascii_case(word)
+            }
+        })
+    };
+    if matches(language.flow_keywords) {
+        Some(Class::KeywordFlow)
+    } else if matches(language.types) {
+        Some(Class::KeywordType)
+    } else if matches(language.keywords) {
+        Some(Class::Keyword)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The highlighted tokens of `code` with their text.
+    fn tokens<'a>(code: &'a str, language: &Language) -> Vec<(&'a str, Class)> {
+        tokenize(code, language)
+            .into_iter()
+            .map(|(range, class)| (&code[range], class))
+            .collect()
+    }
+
+    #[test]
+    fn cpp() {
+        let code = "#include <vector>\nint f(char c) { // note\n  return c == 'x' ? \"a\\\"b\" : /* no */ 0;\n}";
+        assert_eq!(
+            tokens(code, &CPP),
+            [
+                ("#include <vector>", Class::Preprocessor),
+                ("int", Class::KeywordType),
+                ("char", Class::KeywordType),
+                ("// note", Class::Comment),
+                ("return", Class::KeywordFlow),
+                ("'x'", Class::CharLiteral),
+                ("\"a\\\"b\"", Class::StringLiteral),
+                ("/* no */", Class::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn cpp_hash_inside_a_line_is_not_preprocessor() {
+        assert_eq!(tokens("a # b", &CPP), []);
+        assert_eq!(
+            tokens("  #define X", &CPP),
+            [("#define X", Class::Preprocessor)]
+        );
+    }
+
+    #[test]
+    fn python() {
+        let code = "def f(x):\n    \"\"\"Doc\n    string\"\"\"\n    return x // 2  # half";
+        assert_eq!(
+            tokens(code, &PYTHON),
+            [
+                ("def", Class::Keyword),
+                ("\"\"\"Doc\n    string\"\"\"", Class::StringLiteral),
+                ("return", Class::KeywordFlow),
+                ("# half", Class::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn shell() {
+        let code = "if curl https://example.com/#top; then echo 'ok' # done\nfi";
+        assert_eq!(
+            tokens(code, &SHELL),
+            [
+                ("if", Class::KeywordFlow),
+                ("then", Class::KeywordFlow),
+                ("echo", Class::Keyword),
+                ("'ok'", Class::StringLiteral),
+                ("# done", Class::Comment),
+                ("fi", Class::KeywordFlow),
+            ]
+        );
+    }
+
+    #[test]
+    fn cmake_keywords_ignore_case() {
+        assert_eq!(
+            tokens("IF(X STREQUAL \"y\")", &CMAKE),
+            [
+                ("IF", Class::KeywordFlow),
+                ("STREQUAL", Class::KeywordType),
+                ("\"y\"", Class::StringLiteral),
+            ]
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            tokens("{\"a\": true, \"b\": null}", &JSON),
+            [
+                ("\"a\"", Class::StringLiteral),
+                ("true", Class::KeywordType),
+                ("\"b\"", Class::StringLiteral),
+                ("null", Class::KeywordType),
+            ]
+        );
+    }
+
+    #[test]
+    fn generic_does_not_treat_double_slash_as_comment() {
+        assert_eq!(
+            tokens("see http://example.com \"quoted\" # note", &GENERIC),
+            [
+                ("\"quoted\"", Class::StringLiteral),
+                ("# note", Class::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_string_ends_at_line_end() {
+        assert_eq!(
+            tokens("\"open\nint", &CPP),
+            [
+                ("\"open", Class::StringLiteral),
+                ("int", Class::KeywordType)
+            ]
+        );
+    }
+
+    #[test]
+    fn languages_by_file_name() {
+        let is = |name: &str, language: &Language| {
+            // consts have no fixed address to compare
+            language_for(name)
+                .is_some_and(|l| l.keywords == language.keywords && l.types == language.types)
+        };
+        assert!(is("api.h", &CPP));
+        assert!(is("src/Impl.CPP", &CPP));
+        assert!(is(".py", &PYTHON));
+        assert!(is("python", &PYTHON));
+        assert!(is("CMakeLists.txt", &CMAKE));
+        assert!(is("build.sh", &SHELL));
+        assert!(language_for("notes.txt").is_none());
+    }
+
+    #[test]
+    fn links_are_split_at_token_boundaries() {
+        let links = [Link {
+            range: 0..5,
+            ref_id: "r".to_owned(),
+        }];
+        assert_eq!(
+            highlight_with_links("int x", &CPP, &links),
+            "<span class=\"highlight-keywordtype\"><a href=\"refid://r\">int</a></span>\
+             <span class=\"highlight-normal\"><a href=\"refid://r\"> x</a></span>"
+        );
+    }
+}
@@ -1,69 +1,298 @@
 use clap::Parser;
+use config::{Config, Layout, MemberLayout};
+use error::DoxyError;
+use parser::NodeExt;
 use rayon::prelude::*;
 use roxmltree::Document;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use tera::Tera;
 
+mod broken_links;
+mod cache;
+mod config;
+mod diagram;
+mod error;
+mod hash;
+mod highlight;
+mod math;
 mod parser;
+mod search;
+mod serve;
+mod theme;
 
 #[derive(Debug, Parser)]
 struct Cli {
-    /// Root directory for the doxygen XML (required)
-    #[arg(long)]
-    source: String,
+    /// Project configuration file [default: doxyrs.toml, if present]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
 
-    /// Directory containing the doxygen XML output (required)
-    #[arg(long)]
-    xml: String,
+    /// Root directory for the doxygen XML (required, here or in the config file)
+    #[arg(long, global = true)]
+    source: Option<PathBuf>,
 
-    /// HTML output directory (required)
-    #[arg(long)]
-    output: String,
+    /// Directory containing the doxygen XML output (required, here or in the config file)
+    #[arg(long, global = true)]
+    xml: Option<PathBuf>,
+
+    /// HTML output directory (required, here or in the config file)
+    #[arg(long, global = true)]
+    output: Option<PathBuf>,
+
+    /// Directory with templates/ and static/ overriding or adding to the built-in ones
+    #[arg(long, global = true)]
+    theme_dir: Option<PathBuf>,
+
+    /// Render classes and namespaces inline on their file's page, or on pages of their own
+    /// [default: per-file]
+    #[arg(long, value_enum, global = true)]
+    layout: Option<Layout>,
+
+    /// Start each scope with a table of its members and their brief descriptions
+    /// [default: detailed]
+    #[arg(long, value_enum, global = true)]
+    member_layout: Option<MemberLayout>,
+
+    /// Comma-separated protection levels of the members to document [default: public]
+    #[arg(long, value_enum, value_delimiter = ',', global = true)]
+    include_protection: Option<Vec<parser::Protection>>,
+
+    /// How to render formulas [default: mathml]
+    #[arg(long, value_enum, global = true)]
+    math: Option<math::MathBackend>,
+
+    /// Also write the broken link report to this JSON file
+    #[arg(long, global = true)]
+    broken_links_json: Option<PathBuf>,
+
+    /// Exit with an error if any link target could not be resolved. `=false` turns off the
+    /// config file's setting [default: false]
+    #[arg(
+        long,
+        global = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    fail_on_broken_links: Option<bool>,
+
+    /// Ignore the build cache and render every page
+    #[arg(long, global = true)]
+    full_rebuild: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Build, then serve the output directory and rebuild whenever the input changes
+    Serve {
+        /// Port on localhost to serve the documentation on
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+    },
+}
+
+/// Everything a build needs, from the command line and the config file.
+struct Settings {
+    /// The config file the settings were read from, if any
+    config_file: Option<PathBuf>,
+    source_dir: PathBuf,
+    xml_dir: PathBuf,
+    html_dir: PathBuf,
+    theme_dir: Option<PathBuf>,
+    project: config::Project,
+    layout: Layout,
+    member_layout: MemberLayout,
+    options: parser::ParseOptions,
+    broken_links_json: Option<PathBuf>,
+    fail_on_broken_links: bool,
+    full_rebuild: bool,
+    /// Add the script that reloads the page when `serve` rebuilds it
+    live_reload: bool,
 }
 
 fn main() {
     let opt = Cli::parse();
+    let mut settings = match load_settings(&opt) {
+        Ok(settings) => settings,
+        Err(message) => {
+            println!("{}", message);
+            std::process::exit(1);
+        }
+    };
 
-    let source_dir = PathBuf::from(opt.source);
+    match opt.command {
+        None => {
+            if !build(&settings) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Serve { port }) => {
+            settings.live_reload = true;
+            serve::run(settings, port, || load_settings(&opt));
+        }
+    }
+}
+
+/// Combines the command line with the config file. Errors are messages for the user.
+fn load_settings(opt: &Cli) -> Result<Settings, String> {
+    let config_file = match &opt.config {
+        Some(file_name) => Some(file_name.clone()),
+        None if Path::new(config::DEFAULT_FILE_NAME).exists() => {
+            Some(PathBuf::from(config::DEFAULT_FILE_NAME))
+        }
+        None => None,
+    };
+    let config = match &config_file {
+        Some(file_name) => Config::load(file_name).map_err(|e| format!("ERROR: {}", e))?,
+        None => Config::default(),
+    };
+
+    let required = |value: Option<PathBuf>, flag: &str, key: &str| {
+        value.ok_or_else(|| {
+            format!(
+                "--{} is required, either on the command line or as {} in {}",
+                flag,
+                key,
+                config::DEFAULT_FILE_NAME
+            )
+        })
+    };
+    let source_dir = required(
+        opt.source.clone().or(config.input.source),
+        "source",
+        "input.source",
+    )?;
+    let xml_dir = required(opt.xml.clone().or(config.input.xml), "xml", "input.xml")?;
+    let html_dir = required(
+        opt.output.clone().or(config.output.dir),
+        "output",
+        "output.dir",
+    )?;
+    let theme_dir = opt.theme_dir.clone().or(config.output.theme_dir);
 
-    let xml_dir = PathBuf::from(opt.xml);
     if !xml_dir.exists() {
-        println!("--xml path not found: {}", xml_dir.to_string_lossy());
-        std::process::exit(1);
+        return Err(format!(
+            "--xml path not found: {}",
+            xml_dir.to_string_lossy()
+        ));
     }
 
-    let html_dir = PathBuf::from(opt.output);
-    std::fs::create_dir_all(html_dir.join("images")).unwrap();
+    let layout = opt
+        .layout
+        .or(config.output.layout)
+        .unwrap_or(Layout::PerFile);
+    let options = parser::ParseOptions {
+        inline_scopes: layout == Layout::PerFile,
+        protections: opt
+            .include_protection
+            .clone()
+            .or(config.filter.include_protection)
+            .unwrap_or_else(|| vec![parser::Protection::Public]),
+        math: opt.math.or(config.output.math).unwrap_or_default(),
+    };
+    Ok(Settings {
+        config_file,
+        source_dir,
+        xml_dir,
+        html_dir,
+        theme_dir,
+        project: config.project,
+        layout,
+        member_layout: opt
+            .member_layout
+            .or(config.output.member_layout)
+            .unwrap_or_default(),
+        options,
+        broken_links_json: opt
+            .broken_links_json
+            .clone()
+            .or(config.check.broken_links_json),
+        fail_on_broken_links: opt
+            .fail_on_broken_links
+            .unwrap_or(config.check.fail_on_broken_links),
+        full_rebuild: opt.full_rebuild,
+        live_reload: false,
+    })
+}
+
+/// Generates the documentation. Returns false if anything failed.
+fn build(settings: &Settings) -> bool {
+    let source_dir = &settings.source_dir;
+    let xml_dir = &settings.xml_dir;
+    let html_dir = &settings.html_dir;
+    let theme_dir = &settings.theme_dir;
+    let layout = settings.layout;
+    let options = &settings.options;
+
+    if let Err(e) = std::fs::create_dir_all(html_dir.join("images")) {
+        println!("ERROR: {}", DoxyError::io(html_dir, e));
+        return false;
+    }
+
+    let mut project = settings.project.clone();
+    let logo = project.logo.as_ref().map(PathBuf::from);
+    let assets = match theme::write_static_files(html_dir, theme_dir.as_deref(), logo.as_deref()) {
+        Ok(assets) => assets,
+        Err(e) => {
+            println!("ERROR: could not copy static files: {}", e);
+            return false;
+        }
+    };
 
-    copy_static_files(&html_dir).unwrap();
+    if let Some(logo) = &logo {
+        let name = logo.file_name().unwrap_or_default().to_string_lossy();
+        project.logo = Some(assets.urls[name.as_ref()].clone());
+    }
+    let mut base_context = tera::Context::new();
+    base_context.insert("project", &project);
+    base_context.insert("assets", &assets.urls);
+    base_context.insert("extra_css", &assets.extra_css);
+    base_context.insert("extra_js", &assets.extra_js);
+    base_context.insert("live_reload", &settings.live_reload);
+    base_context.insert("member_layout", &settings.member_layout);
 
     // read the index file
     let index_path = xml_dir.join("index.xml");
-    let content = std::fs::read_to_string(&index_path).unwrap();
-    let doc = Document::parse(&content).unwrap();
+    let content = match std::fs::read_to_string(&index_path) {
+        Ok(content) => content,
+        Err(e) => {
+            println!("ERROR: {}", DoxyError::io(&index_path, e));
+            return false;
+        }
+    };
+    let doc = match Document::parse(&content) {
+        Ok(doc) => doc,
+        Err(source) => {
+            let file = index_path.clone();
+            println!("ERROR: {}", DoxyError::Xml { file, source });
+            return false;
+        }
+    };
 
     // parse all compounds
-    let index = doc
-        .root()
-        .children()
-        .find(|n| n.has_tag_name("doxygenindex"))
-        .unwrap();
+    let index = doc.root_element();
 
     let compound_nodes: Vec<_> = index
         .children()
         .filter(|n| {
             n.has_tag_name("compound")
-                && n.attribute("kind")
-                    .map(|kind| kind == "file" || kind == "page")
-                    .unwrap()
+                && match n.attribute("kind") {
+                    Some("file") | Some("page") => true,
+                    Some("class") | Some("struct") | Some("union") | Some("namespace") => {
+                        layout == Layout::PerScope
+                    }
+                    _ => false,
+                }
         })
         .collect();
     // TODO: we could use par_bridge if we don't care about the order of nodes. Right now we do.
-    let compounds: Vec<Compound> = compound_nodes
+    let results: Vec<Result<Option<Compound>, DoxyError>> = compound_nodes
         .par_iter()
         //.iter()
-        .filter_map(|compound| {
+        .map(|compound| {
             /*let name = compound
                 .children()
                 .find(|n| n.has_tag_name("name"))
@@ -73,188 +302,817 @@ fn main() {
             if name != "mwVerifierNestedEnums.hpp" {
                 return None;
             }*/
-            let ref_id = compound.attribute("refid").unwrap();
-            let kind = compound.attribute("kind").unwrap();
+            let ref_id = compound.attr("refid").map_err(|e| e.in_file(&index_path))?;
+            let kind = compound.attr("kind").map_err(|e| e.in_file(&index_path))?;
             match kind {
                 "file" => {
-                    let file = parser::parse_compound_file(&xml_dir, ref_id);
+                    let file = parser::parse_compound_file(xml_dir, ref_id, options)?;
                     if file.scopes.is_empty() {
                         //println!("{} is empty", file.ref_id);
-                        None
+                        Ok(None)
                     } else {
-                        Some(Compound::File(file))
+                        Ok(Some(Compound::File(file)))
                     }
                 }
-                "page" => Some(Compound::Page(parser::parse_compound_page(
-                    &xml_dir, ref_id,
-                ))),
-                _ => unimplemented!(),
+                "page" => Ok(Some(Compound::Page(parser::parse_compound_page(
+                    xml_dir, ref_id, options,
+                )?))),
+                "class" | "struct" | "union" | "namespace" => Ok(Some(Compound::Scope(Box::new(
+                    parser::parse_compound_scope_page(xml_dir, ref_id, options)?,
+                )))),
+                _ => Err(DoxyError::unsupported_kind(*compound, kind).in_file(&index_path)),
             }
         })
         .collect();
 
-    write_navigation(&html_dir, &compounds);
+    // all errors, and how many of them are about a compound, as opposed to the shared files
+    let mut errors = vec![];
+    let mut n_failed = 0;
+    let mut compounds = vec![];
+    for result in results {
+        match result {
+            Ok(Some(compound)) => {
+                for warning in &compound.common().warnings {
+                    println!("WARNING: {}", warning);
+                }
+                compounds.push(compound);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                n_failed += 1;
+                errors.push(e);
+            }
+        }
+    }
 
-    let tera = tera::Tera::new("templates/*.html").unwrap();
-    let relink = create_relinker(&source_dir, &html_dir, &compounds);
+    if let Err(e) = write_navigation(html_dir, &compounds) {
+        errors.push(DoxyError::io(&html_dir.join("nav.js"), e));
+    }
 
-    compounds.into_iter().par_bridge().for_each(|compound| {
-        match compound {
-            Compound::File(mut file) => {
-                let file_dir = file.common.source.rsplit_once('/').unwrap().0;
-                let file_path = source_dir.join(&file.common.source);
-                let file_path = file_path.to_str().unwrap();
-
-                // update deferred links
-                for scope in &mut file.scopes {
-                    for section in &mut scope.sections {
-                        for member in &mut section.members {
-                            member.definition = relink(&member.definition, file_dir, file_path);
-                            member.description = relink(&member.description, file_dir, file_path);
-                        }
+    let (tera, templates_hash) = match theme::load_templates(theme_dir.as_deref()) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("ERROR: {}", e);
+            return false;
+        }
+    };
+    let (ref_to_path, ref_to_tooltip) = create_ref_to_path_map(&compounds);
+    // pages linking to a ref show its tooltip, so they are outdated when it changes too
+    let refs: std::collections::HashMap<_, _> = ref_to_path
+        .iter()
+        .map(|(ref_id, path)| {
+            let target = match ref_to_tooltip.get(ref_id) {
+                Some(tooltip) => format!("{} {}", path, hash::content_hash(tooltip.as_bytes())),
+                None => path.clone(),
+            };
+            (ref_id.clone(), target)
+        })
+        .collect();
+
+    // find the compounds whose pages need to be written
+    let build_settings = format!(
+        "{} {} {:?} {:?}",
+        templates_hash,
+        base_context.clone().into_json(),
+        layout,
+        options
+    );
+    let mut manifest = cache::Manifest {
+        settings: hash::content_hash(build_settings.as_bytes()),
+        refs,
+        compounds: Default::default(),
+    };
+    // loaded for removing stale pages even when it is not trusted for skipping any
+    let old_manifest = cache::Manifest::load(html_dir);
+    let re_links = regex::Regex::new(r#"refid://([^"\\]*)"#).unwrap();
+    let re_imgs = regex::Regex::new(r#"doxyimg://([^"\\]*)"#).unwrap();
+    let compound_inputs: Vec<_> = compounds
+        .par_iter()
+        .map(|compound| {
+            let common = compound.common();
+            let html = compound_html(compound);
+            // copied images get content-hashed names, so pages are outdated when an image changes
+            let mut inputs = common.inputs.clone();
+            let mut images = vec![];
+            for caps in re_imgs.captures_iter(&html) {
+                let img_dir = source_file_dir(&common.source);
+                if let Some(source) = image_source(source_dir, img_dir, &caps[1]) {
+                    // a missing image gets an empty hash, which changes once it shows up
+                    let hash = std::fs::read(&source)
+                        .map(|content| hash::content_hash(&content))
+                        .unwrap_or_default();
+                    if !hash.is_empty() {
+                        let name = source.file_name().unwrap_or_default().to_string_lossy();
+                        images.push(hash::with_hash(&name, &hash));
                     }
+                    inputs.insert(format!("image:{}", source.display()), hash);
                 }
+            }
+            let links = re_links
+                .captures_iter(&html)
+                .map(|caps| caps[1].to_owned())
+                .collect();
+            let up_to_date = !settings.full_rebuild
+                && old_manifest.settings == manifest.settings
+                && old_manifest.is_up_to_date(&common.ref_id, &inputs, &manifest.refs)
+                && html_dir.join(html_file_name(&common.ref_id)).exists();
+            (inputs, links, images, up_to_date)
+        })
+        .collect();
+    remove_stale_pages(html_dir, &old_manifest, &compounds);
+    // skipped pages keep using the images they were written with
+    let referenced_images: std::collections::HashSet<String> = compound_inputs
+        .iter()
+        .flat_map(|(_, _, images, _)| images.iter().cloned())
+        .collect();
+    if let Err(e) = search::write_search_index(html_dir, &compounds, &ref_to_path) {
+        errors.push(DoxyError::io(&html_dir.join("search"), e));
+    }
+    if let Err(e) = search::write_text_index(html_dir, &compounds, &ref_to_path) {
+        errors.push(DoxyError::io(&html_dir.join("search").join("text"), e));
+    }
+    let has_main_page = compounds
+        .iter()
+        .any(|c| matches!(c, Compound::Page(page) if page.common.ref_id == MAIN_PAGE_REF_ID));
+    if !has_main_page {
+        if let Err(e) = write_overview(&tera, &base_context, html_dir, &compounds, &ref_to_path) {
+            errors.push(e);
+        }
+    }
+    let broken_links = broken_links::BrokenLinks::default();
+    let relink = create_relinker(
+        source_dir,
+        html_dir,
+        ref_to_path,
+        ref_to_tooltip,
+        &broken_links,
+    );
 
-                let file_name = html_dir.join(format!("{}.html", file.common.ref_id));
-                write_compound_file(&tera, &file_name, &file);
+    let mut to_render = vec![];
+    let mut n_up_to_date = 0;
+    for (compound, (inputs, links, _, up_to_date)) in compounds.into_iter().zip(compound_inputs) {
+        let ref_id = compound.common().ref_id.clone();
+        if up_to_date {
+            // its broken links are still there, so they are reported again
+            n_up_to_date += 1;
+            for link in &old_manifest.compounds[&ref_id].broken_links {
+                broken_links.add(link.clone());
             }
-            Compound::Page(mut page) => {
-                let file_dir = page
-                    .common
-                    .source
-                    .rsplit_once('/')
-                    .map(|x| x.0)
-                    .unwrap_or(".");
-                let file_path = source_dir.join(&page.common.source);
-                let file_path = file_path.to_str().unwrap();
-
-                // update deferred links
-                page.description = relink(&page.description, file_dir, file_path);
-
-                let file_name = html_dir.join(format!("{}.html", page.common.ref_id));
-                write_compound_page(&tera, &file_name, &page);
+        } else {
+            to_render.push(compound);
+        }
+        let entry = cache::CompoundEntry {
+            inputs,
+            links,
+            broken_links: vec![],
+        };
+        manifest.compounds.insert(ref_id, entry);
+    }
+
+    let results: Vec<(String, Result<(), DoxyError>)> = to_render
+        .into_par_iter()
+        .map(|compound| {
+            let ref_id = compound.common().ref_id.clone();
+            let result = match compound {
+                Compound::File(mut file) => {
+                    let file_dir = source_file_dir(&file.common.source);
+                    let page = html_file_name(&file.common.ref_id);
+                    let site = LinkSite {
+                        img_dir: file_dir,
+                        page: &page,
+                        source: &file.common.source,
+                        member: None,
+                        in_svg: false,
+                    };
+
+                    // update deferred links
+                    for scope in &mut file.scopes {
+                        relink_scope(scope, &relink, &site);
+                    }
+
+                    let file_name = html_dir.join(&page);
+                    write_compound_file(&tera, &base_context, &file_name, &file)
+                }
+                Compound::Scope(mut scope_page) => {
+                    let file_dir = source_file_dir(&scope_page.common.source);
+                    let page = html_file_name(&scope_page.common.ref_id);
+                    let site = LinkSite {
+                        img_dir: file_dir,
+                        page: &page,
+                        source: &scope_page.common.source,
+                        member: None,
+                        in_svg: false,
+                    };
+
+                    // update deferred links
+                    relink_scope(&mut scope_page.scope, &relink, &site);
+
+                    let file_name = html_dir.join(&page);
+                    write_compound_scope(&tera, &base_context, &file_name, &scope_page)
+                }
+                Compound::Page(mut page) => {
+                    let file_dir = source_file_dir(&page.common.source);
+                    let page_name = html_file_name(&page.common.ref_id);
+                    let site = LinkSite {
+                        img_dir: file_dir,
+                        page: &page_name,
+                        source: &page.common.source,
+                        member: None,
+                        in_svg: false,
+                    };
+
+                    // update deferred links
+                    page.brief = relink(&page.brief, &site);
+                    page.details = relink(&page.details, &site);
+                    page.description = format!("{}{}", page.brief, page.details);
+
+                    let file_name = html_dir.join(&page_name);
+                    write_compound_page(&tera, &base_context, &file_name, &page)
+                }
+            };
+            (ref_id, result)
+        })
+        .collect();
+    let mut n_written = 0;
+    for (ref_id, result) in results {
+        match result {
+            Ok(()) => n_written += 1,
+            Err(e) => {
+                // render it again next time
+                manifest.compounds.remove(&ref_id);
+                n_failed += 1;
+                errors.push(e);
             }
         }
-    });
+    }
+
+    drop(relink);
+    remove_unreferenced_images(&html_dir.join("images"), &referenced_images);
+    let broken_links = broken_links.into_sorted();
+    let page_to_ref: std::collections::HashMap<String, String> = manifest
+        .compounds
+        .keys()
+        .map(|ref_id| (html_file_name(ref_id), ref_id.clone()))
+        .collect();
+    for link in &broken_links {
+        if let Some(ref_id) = page_to_ref.get(&link.page) {
+            let entry = manifest.compounds.get_mut(ref_id).unwrap();
+            entry.broken_links.push(link.clone());
+        }
+    }
+    if let Err(e) = manifest.save(html_dir) {
+        errors.push(DoxyError::io(&html_dir.join(cache::FILE_NAME), e));
+    }
+    if !broken_links.is_empty() {
+        broken_links::print_report(&broken_links);
+    }
+    if let Some(json_file) = &settings.broken_links_json {
+        if let Err(e) = broken_links::write_json(json_file, &broken_links) {
+            errors.push(DoxyError::io(json_file, e));
+        }
+    }
+
+    for error in &errors {
+        println!("ERROR: {}", error);
+    }
+    let n_other_errors = errors.len() - n_failed;
+    println!(
+        "{} compounds written, {} up to date, {} failed{}",
+        n_written,
+        n_up_to_date,
+        n_failed,
+        match n_other_errors {
+            0 => String::new(),
+            1 => ", 1 other error".to_owned(),
+            n => format!(", {} other errors", n),
+        }
+    );
+    let broken_links_ok = !settings.fail_on_broken_links || broken_links.is_empty();
+    errors.is_empty() && broken_links_ok
+}
+
+/// Doxygen's mainpage becomes the landing page of the output directory.
+const MAIN_PAGE_REF_ID: &str = "indexpage";
+
+fn html_file_name(ref_id: &str) -> String {
+    if ref_id == MAIN_PAGE_REF_ID {
+        "index.html".to_owned()
+    } else {
+        format!("{}.html", ref_id)
+    }
 }
 
 enum Compound {
     File(parser::File),
     Page(parser::Page),
+    Scope(Box<parser::ScopePage>),
+}
+
+impl Compound {
+    fn common(&self) -> &parser::PageCommon {
+        match self {
+            Compound::File(file) => &file.common,
+            Compound::Page(page) => &page.common,
+            Compound::Scope(scope_page) => &scope_page.common,
+        }
+    }
 }
 
-type RelinkFn = dyn Fn(&str, &str, &str) -> String + Sync;
-fn create_relinker(source_dir: &Path, html_dir: &Path, compounds: &[Compound]) -> Box<RelinkFn> {
-    let re_refs = regex::Regex::new("refid://([^\"]*)").unwrap();
+/// Everything a compound's page is rendered from, as JSON, for finding the refs and images it
+/// references.
+fn compound_html(compound: &Compound) -> String {
+    let value = match compound {
+        Compound::File(file) => tera::to_value(file),
+        Compound::Page(page) => tera::to_value(page),
+        Compound::Scope(scope_page) => tera::to_value(scope_page),
+    };
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Directory of a compound's source file, which its image paths are relative to.
+fn source_file_dir(source: &str) -> &str {
+    source.rsplit_once('/').map(|x| x.0).unwrap_or(".")
+}
+
+/// Removes the pages of compounds that were written by an earlier run but don't exist any more.
+fn remove_stale_pages(html_dir: &Path, old_manifest: &cache::Manifest, compounds: &[Compound]) {
+    let current: std::collections::HashSet<&str> = compounds
+        .iter()
+        .map(|c| c.common().ref_id.as_str())
+        .collect();
+    for ref_id in old_manifest.compounds.keys() {
+        if !current.contains(ref_id.as_str()) {
+            let file_name = html_dir.join(html_file_name(ref_id));
+            match std::fs::remove_file(&file_name) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => println!("WARNING: could not remove {}: {}", file_name.display(), e),
+            }
+        }
+    }
+}
+
+/// Removes images copied by an earlier run that no page references any more, like old versions
+/// of changed images.
+fn remove_unreferenced_images(images_dir: &Path, referenced: &std::collections::HashSet<String>) {
+    let entries = match std::fs::read_dir(images_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("WARNING: could not list {}: {}", images_dir.display(), e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !referenced.contains(&name) {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                println!(
+                    "WARNING: could not remove {}: {}",
+                    entry.path().display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+fn relink_scope(scope: &mut parser::Scope, relink: &RelinkFn, site: &LinkSite) {
+    // `description` is `brief` followed by `details`; relinking it too would report every broken
+    // link twice
+    scope.brief = relink(&scope.brief, site);
+    scope.details = relink(&scope.details, site);
+    scope.description = format!("{}{}", scope.brief, scope.details);
+    for class_ref in scope.bases.iter_mut().chain(scope.derived.iter_mut()) {
+        class_ref.name = relink(&class_ref.name, site);
+    }
+    if let Some(diagram) = &mut scope.diagram {
+        let site = LinkSite {
+            in_svg: true,
+            ..*site
+        };
+        *diagram = relink(diagram, &site);
+    }
+    for group in &mut scope.inherited {
+        group.name = relink(&group.name, site);
+        for member in &mut group.members {
+            member.name = relink(&member.name, site);
+        }
+    }
+    for section in &mut scope.sections {
+        if let Some(description) = &mut section.description {
+            *description = relink(description, site);
+        }
+        for member in section.members_mut() {
+            let member_name = if scope.kind == "file" {
+                member.name.clone()
+            } else {
+                format!("{}::{}", scope.qualified_name, member.name)
+            };
+            let site = LinkSite {
+                member: Some(&member_name),
+                ..*site
+            };
+            member.definition = relink(&member.definition, &site);
+            member.brief = relink(&member.brief, &site);
+            member.details = relink(&member.details, &site);
+            member.description = format!("{}{}", member.brief, member.details);
+            for enum_value in &mut member.enum_values {
+                enum_value.brief = relink(&enum_value.brief, &site);
+                enum_value.details = relink(&enum_value.details, &site);
+                enum_value.description = format!("{}{}", enum_value.brief, enum_value.details);
+            }
+        }
+    }
+}
+
+/// Where the HTML passed to the relinker ends up, for resolving images and reporting broken links.
+#[derive(Clone, Copy)]
+struct LinkSite<'a> {
+    /// Directory of the source file, relative to the source root
+    img_dir: &'a str,
+    page: &'a str,
+    source: &'a str,
+    member: Option<&'a str>,
+    /// SVG links get their tooltip as a `<title>` element, browsers ignore the attribute there
+    in_svg: bool,
+}
+
+type RelinkFn<'a> = dyn Fn(&str, &LinkSite) -> String + Sync + 'a;
+fn create_relinker<'a>(
+    source_dir: &Path,
+    html_dir: &Path,
+    ref_to_path: std::collections::HashMap<String, String>,
+    ref_to_tooltip: std::collections::HashMap<String, String>,
+    broken_links: &'a broken_links::BrokenLinks,
+) -> Box<RelinkFn<'a>> {
+    let re_refs = regex::Regex::new("(?s)<a href=\"refid://([^\"]*)\">(.*?)</a>").unwrap();
     let re_imgs = regex::Regex::new("doxyimg://([^\"]*)").unwrap();
-    let ref_to_path = create_ref_to_path_map(compounds);
-    let source_dir = source_dir.to_str().unwrap().to_owned();
-    let html_dir = html_dir.to_str().unwrap().to_owned();
+    let source_dir = source_dir.to_path_buf();
+    let images_dir = html_dir.join("images");
+    // source path -> file name in images_dir, None if it could not be copied
+    let copied_images =
+        std::sync::Mutex::new(std::collections::HashMap::<PathBuf, Option<String>>::new());
 
-    Box::new(move |v, img_dir, _file_path| -> String {
+    Box::new(move |v, site| -> String {
+        let img_dir = site.img_dir;
         let v = re_refs.replace_all(v, |caps: &regex::Captures| {
-            let cap = &caps[1];
-            ref_to_path.get(cap).map(|s| s.as_str()).unwrap_or_else(|| {
-                // println!("WARNING: {}: ref not found: {}", file_path, cap);
-                "refid://not-found"
-            })
+            let (ref_id, text) = (&caps[1], &caps[2]);
+            match ref_to_path.get(ref_id) {
+                Some(path) => match ref_to_tooltip.get(ref_id) {
+                    Some(tooltip) if site.in_svg => format!(
+                        "<a href=\"{}\"><title>{}</title>{}</a>",
+                        path,
+                        tera::escape_html(tooltip),
+                        text
+                    ),
+                    Some(tooltip) => format!(
+                        "<a href=\"{}\" title=\"{}\">{}</a>",
+                        path,
+                        tera::escape_html(tooltip),
+                        text
+                    ),
+                    None => format!("<a href=\"{}\">{}</a>", path, text),
+                },
+                None => {
+                    broken_links.add(broken_links::BrokenLink {
+                        page: site.page.to_owned(),
+                        source: site.source.to_owned(),
+                        member: site.member.map(|m| m.to_owned()),
+                        ref_id: ref_id.to_owned(),
+                        text: search::strip_html(text),
+                    });
+                    // valid in the HTML of descriptions and in SVG diagrams alike
+                    format!("<a class=\"broken-link\">{}</a>", text)
+                }
+            }
         });
         let v = re_imgs.replace_all(&v, |caps: &regex::Captures| {
-            let rel_path = percent_encoding::percent_decode_str(&caps[1])
-                .decode_utf8()
-                .unwrap();
-            let source = format!("{}/{}/{}", &source_dir, &img_dir, rel_path);
-            if std::path::PathBuf::from(&source).exists() {
-                let filename = source.rsplit('/').next().unwrap();
-                // TODO: ensure uniqueness!
-                let target = format!("{}/images/{}", html_dir, filename);
-                // TODO: check timestamps or similar!
-                if !std::path::PathBuf::from(&target).exists() {
-                    std::fs::copy(&source, target).unwrap();
+            let source = match image_source(&source_dir, img_dir, &caps[1]) {
+                Some(source) => source,
+                None => {
+                    println!(
+                        "WARNING: img path is not valid percent-encoded UTF-8: {} (on {})",
+                        &caps[1], site.page
+                    );
+                    return caps[1].to_owned();
                 }
-                format!("images/{}", filename)
-            } else {
-                println!("WARNING: img not found: {}", source);
-                caps[1].to_owned()
+            };
+            let cached = copied_images.lock().unwrap().get(&source).cloned();
+            let target = cached.unwrap_or_else(|| {
+                let target = if source.exists() {
+                    copy_image(&source, &images_dir)
+                        .map_err(|e| {
+                            println!("WARNING: could not copy img {}: {}", source.display(), e)
+                        })
+                        .ok()
+                } else {
+                    println!("WARNING: img not found: {}", source.display());
+                    None
+                };
+                copied_images
+                    .lock()
+                    .unwrap()
+                    .insert(source.clone(), target.clone());
+                target
+            });
+            match target {
+                Some(file_name) => format!("images/{}", file_name),
+                None => caps[1].to_owned(),
             }
         });
         v.into_owned()
     })
 }
 
-fn create_ref_to_path_map(compounds: &[Compound]) -> std::collections::HashMap<String, String> {
+/// The file an image referenced as `doxyimg://<encoded path>` refers to. `img_dir` is the
+/// directory of the page's source file, relative to `source_dir`. None if the path does not
+/// decode to UTF-8.
+fn image_source(source_dir: &Path, img_dir: &str, encoded_path: &str) -> Option<PathBuf> {
+    let rel_path = percent_encoding::percent_decode_str(encoded_path)
+        .decode_utf8()
+        .ok()?;
+    Some(source_dir.join(img_dir).join(rel_path.as_ref()))
+}
+
+/// Copies an image to `images/name.<content hash>.ext`. Images with the same name in different
+/// directories don't overwrite each other, and identical images are stored once. Returns the new
+/// file name.
+fn copy_image(source: &Path, images_dir: &Path) -> std::io::Result<String> {
+    let content = std::fs::read(source)?;
+    let name = source.file_name().unwrap_or_default().to_string_lossy();
+    let file_name = hash::hashed_file_name(&name, &content);
+    let target = images_dir.join(&file_name);
+    // the name changes with the content, so an existing file is already right
+    if !target.exists() {
+        std::fs::write(&target, &content)?;
+    }
+    Ok(file_name)
+}
+
+/// Maps ref ids to the paths of their pages or anchors, and to the tooltips of links to them:
+/// kind, qualified name or signature, and the brief description on a second line.
+fn create_ref_to_path_map(
+    compounds: &[Compound],
+) -> (
+    std::collections::HashMap<String, String>,
+    std::collections::HashMap<String, String>,
+) {
     let mut ref_to_path = std::collections::HashMap::<String, String>::new();
+    let mut ref_to_tooltip = std::collections::HashMap::<String, String>::new();
     for compound in compounds {
         match compound {
             Compound::File(file) => {
-                let filename = format!("{}.html", file.common.ref_id);
+                let filename = html_file_name(&file.common.ref_id);
                 ref_to_path.insert(file.common.ref_id.clone(), filename.clone());
+                ref_to_tooltip.insert(
+                    file.common.ref_id.clone(),
+                    format!("file {}", file.common.title),
+                );
                 for class in &file.scopes {
-                    ref_to_path.insert(
-                        class.ref_id.clone(),
-                        format!("{}#{}", filename, class.ref_id),
-                    );
-                    for section in &class.sections {
-                        for member in &section.members {
-                            ref_to_path.insert(
-                                member.ref_id.clone(),
-                                format!("{}#{}", filename, member.ref_id),
-                            );
-                            for enum_value in &member.enum_values {
-                                ref_to_path.insert(
-                                    enum_value.ref_id.clone(),
-                                    format!("{}#{}", filename, enum_value.ref_id),
-                                );
-                            }
-                        }
+                    // the file scope shares the file's ref_id
+                    if class.ref_id != file.common.ref_id {
+                        ref_to_path.insert(
+                            class.ref_id.clone(),
+                            format!("{}#{}", filename, class.ref_id),
+                        );
+                        ref_to_tooltip.insert(class.ref_id.clone(), scope_tooltip(class));
                     }
+                    insert_member_paths(&mut ref_to_path, &mut ref_to_tooltip, &filename, class);
                 }
             }
+            Compound::Scope(scope_page) => {
+                let filename = html_file_name(&scope_page.common.ref_id);
+                ref_to_path.insert(scope_page.common.ref_id.clone(), filename.clone());
+                ref_to_tooltip.insert(
+                    scope_page.common.ref_id.clone(),
+                    scope_tooltip(&scope_page.scope),
+                );
+                insert_member_paths(
+                    &mut ref_to_path,
+                    &mut ref_to_tooltip,
+                    &filename,
+                    &scope_page.scope,
+                );
+            }
             Compound::Page(page) => {
-                let filename = format!("{}.html", page.common.ref_id);
+                let filename = html_file_name(&page.common.ref_id);
                 ref_to_path.insert(page.common.ref_id.clone(), filename);
+                ref_to_tooltip.insert(
+                    page.common.ref_id.clone(),
+                    tooltip(page.common.title.clone(), &page.brief),
+                );
                 // TODO: add paragraph links
             }
         }
     }
-    ref_to_path
+    (ref_to_path, ref_to_tooltip)
 }
 
-fn copy_static_files(html_dir: &Path) -> std::io::Result<()> {
-    let target_dir = html_dir.join("static");
-    std::fs::create_dir_all(&target_dir)?;
-
-    let current_exe = std::env::current_exe()?;
-    let my_path = current_exe.parent().unwrap();
-    let source_dir = if my_path.join("../../static").exists() {
-        my_path.join("../../static")
+fn insert_member_paths(
+    ref_to_path: &mut std::collections::HashMap<String, String>,
+    ref_to_tooltip: &mut std::collections::HashMap<String, String>,
+    filename: &str,
+    scope: &parser::Scope,
+) {
+    let prefix = if scope.kind == "file" {
+        String::new()
     } else {
-        my_path.join("static")
+        format!("{}::", scope.qualified_name)
     };
+    for section in &scope.sections {
+        for member in section.members() {
+            ref_to_path.insert(
+                member.ref_id.clone(),
+                format!("{}#{}", filename, member.ref_id),
+            );
+            // qualify the name in the signature
+            let definition = member.definition.replacen(
+                "<span class=\"member_name\">",
+                &format!("<span class=\"member_name\">{}", tera::escape_html(&prefix)),
+                1,
+            );
+            ref_to_tooltip.insert(
+                member.ref_id.clone(),
+                tooltip(
+                    format!("{} {}", member.kind, search::strip_code_html(&definition)),
+                    &member.brief,
+                ),
+            );
+            for enum_value in &member.enum_values {
+                ref_to_path.insert(
+                    enum_value.ref_id.clone(),
+                    format!("{}#{}", filename, enum_value.ref_id),
+                );
+                let mut title = format!("enumvalue {}{}::{}", prefix, member.name, enum_value.name);
+                if let Some(initializer) = &enum_value.initializer {
+                    title.push(' ');
+                    title.push_str(&search::strip_code_html(initializer));
+                }
+                ref_to_tooltip.insert(enum_value.ref_id.clone(), tooltip(title, &enum_value.brief));
+            }
+        }
+    }
+}
 
-    for entry in std::fs::read_dir(source_dir)? {
-        let entry = entry?;
-        let from_path = entry.path();
-        let to_path = target_dir.join(from_path.file_name().unwrap());
-        std::fs::copy(from_path, to_path)?;
+fn scope_tooltip(scope: &parser::Scope) -> String {
+    tooltip(
+        format!("{} {}", scope.kind, scope.qualified_name),
+        &scope.brief,
+    )
+}
+
+/// `title` and the plain text of `brief` below it.
+fn tooltip(mut title: String, brief: &str) -> String {
+    let brief = search::strip_html(brief);
+    if !brief.is_empty() {
+        title.push('\n');
+        title.push_str(&brief);
     }
-    Ok(())
+    title
+}
+
+fn write_compound_file(
+    tera: &Tera,
+    base_context: &tera::Context,
+    file_name: &Path,
+    file: &parser::File,
+) -> Result<(), DoxyError> {
+    let mut context = base_context.clone();
+    context.extend(tera::Context::from_serialize(file).map_err(|e| render_error(file_name, e))?);
+    write_html(tera, "file.html", &context, file_name)
+}
+
+fn write_compound_page(
+    tera: &Tera,
+    base_context: &tera::Context,
+    file_name: &Path,
+    page: &parser::Page,
+) -> Result<(), DoxyError> {
+    let mut context = base_context.clone();
+    context.extend(tera::Context::from_serialize(page).map_err(|e| render_error(file_name, e))?);
+    write_html(tera, "page.html", &context, file_name)
+}
+
+fn write_html(
+    tera: &Tera,
+    template: &str,
+    context: &tera::Context,
+    file_name: &Path,
+) -> Result<(), DoxyError> {
+    let content = tera
+        .render(template, context)
+        .map_err(|e| render_error(file_name, e))?;
+    let content = html_minifier::minify(content)
+        .map_err(|e| render_error(file_name, tera::Error::msg(format!("minifier: {}", e))))?;
+    let mut f = std::fs::File::create(file_name).map_err(|e| DoxyError::io(file_name, e))?;
+    f.write_all(content.as_bytes())
+        .map_err(|e| DoxyError::io(file_name, e))
+}
+
+fn write_compound_scope(
+    tera: &Tera,
+    base_context: &tera::Context,
+    file_name: &Path,
+    scope_page: &parser::ScopePage,
+) -> Result<(), DoxyError> {
+    let mut context = base_context.clone();
+    context
+        .extend(tera::Context::from_serialize(scope_page).map_err(|e| render_error(file_name, e))?);
+    write_html(tera, "scope.html", &context, file_name)
 }
 
-fn write_compound_file(tera: &Tera, file_name: &Path, file: &parser::File) {
-    let context = tera::Context::from_serialize(file).unwrap();
-    let content = tera.render("file.html", &context).unwrap();
-    let content = html_minifier::minify(content).unwrap();
-    let mut f = std::fs::File::create(file_name).unwrap();
-    f.write_all(content.as_bytes()).unwrap();
+#[derive(serde::Serialize)]
+struct OverviewLink {
+    title: String,
+    href: String,
+}
+
+/// Writes an index.html listing the top-level pages, namespaces and files, for projects without a
+/// doxygen mainpage.
+fn write_overview(
+    tera: &Tera,
+    base_context: &tera::Context,
+    html_dir: &Path,
+    compounds: &[Compound],
+    ref_to_path: &std::collections::HashMap<String, String>,
+) -> Result<(), DoxyError> {
+    let subpages: std::collections::HashSet<&str> = compounds
+        .iter()
+        .flat_map(|c| c.common().subpage_refs.iter().map(|r| r.as_str()))
+        .collect();
+    let link = |common: &parser::PageCommon| OverviewLink {
+        title: common.title.clone(),
+        href: html_file_name(&common.ref_id),
+    };
+
+    let mut pages = vec![];
+    let mut namespaces = std::collections::BTreeMap::new();
+    let mut files = vec![];
+    for compound in compounds {
+        let scopes: Vec<&parser::Scope> = match compound {
+            Compound::Page(page) => {
+                if !subpages.contains(page.common.ref_id.as_str()) {
+                    pages.push(link(&page.common));
+                }
+                vec![]
+            }
+            Compound::File(file) => {
+                files.push((&file.common.source, link(&file.common)));
+                file.scopes.iter().collect()
+            }
+            Compound::Scope(scope_page) => vec![&scope_page.scope],
+        };
+        // in the per-file layout, a namespace shows up in every file that adds to it
+        for scope in scopes {
+            if scope.kind == "namespace" && !scope.qualified_name.contains("::") {
+                if let Some(href) = ref_to_path.get(&scope.ref_id) {
+                    namespaces
+                        .entry(scope.qualified_name.clone())
+                        .or_insert_with(|| href.clone());
+                }
+            }
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut context = base_context.clone();
+    context.insert(
+        "common",
+        &parser::PageCommon {
+            ref_id: MAIN_PAGE_REF_ID.to_owned(),
+            source: String::new(),
+            title: "Overview".to_owned(),
+            has_math: false,
+            subpage_refs: vec![],
+            warnings: vec![],
+            inputs: Default::default(),
+        },
+    );
+    context.insert("pages", &pages);
+    context.insert(
+        "namespaces",
+        &namespaces
+            .into_iter()
+            .map(|(title, href)| OverviewLink { title, href })
+            .collect::<Vec<_>>(),
+    );
+    context.insert(
+        "files",
+        &files.into_iter().map(|(_, link)| link).collect::<Vec<_>>(),
+    );
+    write_html(
+        tera,
+        "overview.html",
+        &context,
+        &html_dir.join(html_file_name(MAIN_PAGE_REF_ID)),
+    )
 }
 
-fn write_compound_page(tera: &Tera, file_name: &Path, page: &parser::Page) {
-    let context = tera::Context::from_serialize(page).unwrap();
-    let content = tera.render("page.html", &context).unwrap();
-    let content = html_minifier::minify(content).unwrap();
-    let mut f = std::fs::File::create(file_name).unwrap();
-    f.write_all(content.as_bytes()).unwrap();
+fn render_error(file_name: &Path, source: tera::Error) -> DoxyError {
+    DoxyError::Render {
+        file: file_name.to_owned(),
+        source,
+    }
 }
 
 /*#[derive(Serialize)]
@@ -280,13 +1138,13 @@ fn to_nav_json_recursive(
 ) -> json::JsonValue {
     // page = [[name, href], [subpage1, subpage2, ...]]
 
-    let href = format!("{}.html", common.ref_id);
+    let href = html_file_name(&common.ref_id);
     let this_page = json::array![common.title.as_str(), href];
     let mut subpages = json::array![];
 
     for subpage_ref in common.subpage_refs.iter() {
-        let subpage = ref_to_compound[subpage_ref.as_str()];
-        if let Compound::Page(subpage) = subpage {
+        // subpages that failed to parse are reported elsewhere
+        if let Some(Compound::Page(subpage)) = ref_to_compound.get(subpage_ref.as_str()) {
             let subpage = to_nav_json_recursive(&subpage.common, ref_to_compound);
             subpages.push(subpage).unwrap();
         }
@@ -295,67 +1153,602 @@ fn to_nav_json_recursive(
     json::array![this_page, subpages]
 }
 
-fn write_navigation(html_dir: &Path, compounds: &[Compound]) {
+/// Returns the `[[title, href], [children]]` node named `title` in `section`, inserting a
+/// header without a link if there is none.
+fn nav_child<'a>(section: &'a mut json::JsonValue, title: &str) -> &'a mut json::JsonValue {
+    let mut found_index: Option<usize> = None;
+    for i in 0..section.len() {
+        if section[i][0][0].as_str() == Some(title) {
+            found_index = Some(i);
+        }
+    }
+    match found_index {
+        Some(found_index) => &mut section[found_index],
+        None => {
+            let header = json::array![title, ""];
+            section.push(json::array![header, json::array![]]).unwrap();
+            let last_idx = section.len() - 1;
+            &mut section[last_idx]
+        }
+    }
+}
+
+fn write_navigation(html_dir: &Path, compounds: &[Compound]) -> std::io::Result<()> {
     let mut ref_to_parent = std::collections::HashMap::<&str, &str>::new();
     let mut ref_to_compound = std::collections::HashMap::<&str, &Compound>::new();
     for compound in compounds {
-        let common = match compound {
-            Compound::File(file) => &file.common,
-            Compound::Page(page) => &page.common,
-        };
+        let common = compound.common();
         ref_to_compound.insert(&common.ref_id, compound);
+        // the main page is linked from the sidebar header, its subpages are top-level entries
+        if common.ref_id == MAIN_PAGE_REF_ID {
+            continue;
+        }
         for child in common.subpage_refs.iter() {
             ref_to_parent.insert(child, &common.ref_id);
         }
     }
 
     let mut doc = json::array![];
+    let mut scopes = json::array![];
 
     for compound in compounds {
-        if let Compound::Page(page) = compound {
-            let common = &page.common;
-            if ref_to_parent.contains_key(common.ref_id.as_str()) {
-                continue; // skip non-root pages
+        match compound {
+            Compound::Page(page) => {
+                let common = &page.common;
+                if ref_to_parent.contains_key(common.ref_id.as_str())
+                    || common.ref_id == MAIN_PAGE_REF_ID
+                {
+                    continue; // skip non-root pages
+                }
+                doc.push(to_nav_json_recursive(common, &ref_to_compound))
+                    .unwrap();
             }
-            doc.push(to_nav_json_recursive(common, &ref_to_compound))
-                .unwrap();
-        } else {
-            let common = match compound {
-                Compound::File(file) => &file.common,
-                Compound::Page(page) => &page.common,
-            };
+            Compound::File(file) => {
+                let common = &file.common;
 
-            let snippets: Vec<&str> = common.source.split('/').collect();
-            let section =
-                snippets[0..snippets.len() - 1]
+                let snippets: Vec<&str> = common.source.split('/').collect();
+                let section = snippets[0..snippets.len() - 1]
                     .iter()
                     .fold(&mut doc, |section, snippet| {
-                        let mut found_index: Option<usize> = None;
-                        for i in 0..section.len() {
-                            if section[i][0][0].as_str().unwrap() == *snippet {
-                                found_index = Some(i);
-                            }
-                        }
-                        match found_index {
-                            Some(found_index) => &mut section[found_index][1],
-                            None => {
-                                let header = json::array![*snippet, ""];
-                                section.push(json::array![header, json::array![]]).unwrap();
-                                let last_idx = section.len() - 1;
-                                &mut section[last_idx][1]
-                            }
-                        }
+                        &mut nav_child(section, snippet)[1]
                     });
-            let href = format!("{}.html", common.ref_id);
-            let this_page = json::array![common.title.as_str(), href.as_str()];
-            section
-                .push(json::array![this_page, json::array![]])
-                .unwrap();
+                let href = html_file_name(&common.ref_id);
+                let this_page = json::array![common.title.as_str(), href.as_str()];
+                section
+                    .push(json::array![this_page, json::array![]])
+                    .unwrap();
+            }
+            Compound::Scope(scope_page) => {
+                // nest classes below their namespaces (or enclosing classes). The parent may come
+                // later in the index, so it is created as a header and gets its link once it shows up.
+                let common = &scope_page.common;
+                let snippets: Vec<&str> = common.title.split("::").collect();
+                let section = snippets[0..snippets.len() - 1]
+                    .iter()
+                    .fold(&mut scopes, |section, snippet| {
+                        &mut nav_child(section, snippet)[1]
+                    });
+                let this_page = nav_child(section, snippets[snippets.len() - 1]);
+                this_page[0][1] = html_file_name(&common.ref_id).into();
+            }
         }
     }
 
-    let f = std::fs::File::create(html_dir.join("nav.js")).unwrap();
+    if !scopes.is_empty() {
+        doc.push(json::array![json::array!["API Reference", ""], scopes])
+            .unwrap();
+    }
+
+    let f = std::fs::File::create(html_dir.join("nav.js"))?;
     let mut f = std::io::BufWriter::new(f);
-    f.write_all(b"let nav=").unwrap();
-    doc.write(&mut f).unwrap();
+    f.write_all(b"let nav=")?;
+    doc.write(&mut f)
+}
+
+/// A new, empty directory for the files of the test `name`.
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("doxyrs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fail_on_broken_links_flag_does_not_take_the_next_argument() {
+        let cli = Cli::try_parse_from([
+            "doxyrs",
+            "--fail-on-broken-links",
+            "serve",
+            "--port",
+            "8123",
+        ])
+        .unwrap();
+        assert_eq!(cli.fail_on_broken_links, Some(true));
+        assert!(matches!(cli.command, Some(Command::Serve { port: 8123 })));
+
+        let cli = Cli::try_parse_from(["doxyrs", "--fail-on-broken-links=false"]).unwrap();
+        assert_eq!(cli.fail_on_broken_links, Some(false));
+
+        let cli = Cli::try_parse_from(["doxyrs"]).unwrap();
+        assert_eq!(cli.fail_on_broken_links, None);
+    }
+
+    const SITE: LinkSite = LinkSite {
+        img_dir: "include",
+        page: "page.html",
+        source: "include/a.h",
+        member: None,
+        in_svg: false,
+    };
+
+    /// Relinks `html` with one known ref, `classA`, which has a tooltip.
+    fn relink(html: &str, site: &LinkSite) -> (String, Vec<broken_links::BrokenLink>) {
+        let ref_to_path = [("classA".to_owned(), "classA.html".to_owned())].into();
+        let ref_to_tooltip = [("classA".to_owned(), "class A\n<b>".to_owned())].into();
+        let broken_links = broken_links::BrokenLinks::default();
+        let relink = create_relinker(
+            Path::new("src"),
+            Path::new("html"),
+            ref_to_path,
+            ref_to_tooltip,
+            &broken_links,
+        );
+        let html = relink(html, site);
+        drop(relink);
+        (html, broken_links.into_sorted())
+    }
+
+    #[test]
+    fn relinker_resolves_refs_with_tooltips() {
+        let (html, broken) = relink("see <a href=\"refid://classA\">A</a>", &SITE);
+        assert_eq!(
+            html,
+            "see <a href=\"classA.html\" title=\"class A\n&lt;b&gt;\">A</a>"
+        );
+        assert!(broken.is_empty());
+    }
+
+    #[test]
+    fn relinker_reports_unknown_refs() {
+        let site = LinkSite {
+            member: Some("ns::f"),
+            ..SITE
+        };
+        let (html, broken) = relink(
+            "<a href=\"refid://classB\">B<b>.</b></a> <a href=\"refid://classA\">A</a>",
+            &site,
+        );
+        assert_eq!(
+            html,
+            "<a class=\"broken-link\">B<b>.</b></a> \
+             <a href=\"classA.html\" title=\"class A\n&lt;b&gt;\">A</a>"
+        );
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].page, "page.html");
+        assert_eq!(broken[0].source, "include/a.h");
+        assert_eq!(broken[0].member.as_deref(), Some("ns::f"));
+        assert_eq!(broken[0].ref_id, "classB");
+        assert_eq!(broken[0].text, "B.");
+    }
+
+    #[test]
+    fn relinked_scopes_rebuild_their_descriptions() {
+        let xml_dir = test_dir("relink-scope");
+        std::fs::write(
+            xml_dir.join("classA.xml"),
+            "<doxygen><compounddef id=\"classA\" kind=\"class\"><compoundname>A</compoundname>\
+             <briefdescription><para>See <ref refid=\"classA\">A</ref>.</para>\
+             </briefdescription><detaileddescription><para>Not \
+             <ref refid=\"missing\">gone</ref>.</para></detaileddescription>\
+             <sectiondef kind=\"public-func\"><memberdef kind=\"function\" id=\"classA_1f\" \
+             prot=\"public\"><type>void</type><name>f</name><argsstring>()</argsstring>\
+             <detaileddescription><para><ref refid=\"missing\">gone</ref></para>\
+             </detaileddescription></memberdef></sectiondef></compounddef></doxygen>",
+        )
+        .unwrap();
+        let options = parser::ParseOptions {
+            inline_scopes: false,
+            protections: vec![parser::Protection::Public],
+            math: math::MathBackend::Mathml,
+        };
+        let mut scope = parser::parse_compound_scope_page(&xml_dir, "classA", &options)
+            .unwrap()
+            .scope;
+
+        let ref_to_path = [("classA".to_owned(), "classA.html".to_owned())].into();
+        let broken_links = broken_links::BrokenLinks::default();
+        let relink = create_relinker(
+            Path::new("src"),
+            Path::new("html"),
+            ref_to_path,
+            Default::default(),
+            &broken_links,
+        );
+        relink_scope(&mut scope, &relink, &SITE);
+        drop(relink);
+
+        assert_eq!(scope.brief, "<p>See <a href=\"classA.html\">A</a>.</p>");
+        assert_eq!(
+            scope.details,
+            "<p>Not <a class=\"broken-link\">gone</a>.</p>"
+        );
+        assert_eq!(
+            scope.description,
+            format!("{}{}", scope.brief, scope.details)
+        );
+        let member = scope.sections[0].members().next().unwrap();
+        assert_eq!(
+            member.description,
+            "<p><a class=\"broken-link\">gone</a></p>"
+        );
+        // each broken link is reported once, not again for the combined description
+        let broken = broken_links.into_sorted();
+        let members: Vec<_> = broken.iter().map(|link| link.member.as_deref()).collect();
+        assert_eq!(members, [None, Some("A::f")]);
+    }
+
+    #[test]
+    fn relinker_puts_svg_tooltips_in_title_elements() {
+        let site = LinkSite {
+            in_svg: true,
+            ..SITE
+        };
+        let (html, _) = relink("<a href=\"refid://classA\"><rect/></a>", &site);
+        assert_eq!(
+            html,
+            "<a href=\"classA.html\"><title>class A\n&lt;b&gt;</title><rect/></a>"
+        );
+    }
+
+    #[test]
+    fn image_paths_that_are_not_utf8_are_rejected() {
+        assert_eq!(
+            image_source(Path::new("src"), "docs", "a%20b.png"),
+            Some(PathBuf::from("src/docs/a b.png"))
+        );
+        assert_eq!(image_source(Path::new("src"), "docs", "a%FF.png"), None);
+    }
+
+    #[test]
+    fn command_line_overrides_the_config_file() {
+        let dir = test_dir("settings");
+        std::fs::create_dir_all(dir.join("xml")).unwrap();
+        let config_file = dir.join("doxyrs.toml");
+        std::fs::write(
+            &config_file,
+            "[input]\nsource = \".\"\nxml = \"xml\"\n[output]\ndir = \"html\"\n\
+             layout = \"per-scope\"\n[check]\nfail_on_broken_links = true\n",
+        )
+        .unwrap();
+        let config_arg = config_file.to_str().unwrap();
+
+        let settings = load_settings(&Cli::parse_from(["doxyrs", "--config", config_arg])).unwrap();
+        assert_eq!(settings.html_dir, dir.join("html"));
+        assert_eq!(settings.layout, Layout::PerScope);
+        assert!(!settings.options.inline_scopes);
+        assert!(settings.fail_on_broken_links);
+        assert_eq!(settings.config_file.as_deref(), Some(config_file.as_path()));
+
+        let settings = load_settings(&Cli::parse_from([
+            "doxyrs",
+            "--config",
+            config_arg,
+            "--output",
+            "elsewhere",
+            "--layout",
+            "per-file",
+            "--fail-on-broken-links=false",
+        ]))
+        .unwrap();
+        assert_eq!(settings.html_dir, PathBuf::from("elsewhere"));
+        assert_eq!(settings.layout, Layout::PerFile);
+        assert!(!settings.fail_on_broken_links);
+    }
+
+    #[test]
+    fn settings_need_the_input_and_output_paths() {
+        let dir = test_dir("settings-missing");
+        let config_file = dir.join("doxyrs.toml");
+        std::fs::write(&config_file, "[input]\nsource = \".\"\n").unwrap();
+        let cli = Cli::parse_from(["doxyrs", "--config", config_file.to_str().unwrap()]);
+        let message = load_settings(&cli).err().unwrap();
+        assert!(message.starts_with("--xml is required"), "{}", message);
+    }
+
+    #[test]
+    fn images_are_stored_by_name_and_content() {
+        let dir = test_dir("copy-image");
+        let images_dir = dir.join("images");
+        std::fs::create_dir_all(&images_dir).unwrap();
+        for (path, content) in &[("a/pic.png", "a"), ("b/pic.png", "b"), ("c/pic.png", "a")] {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let a = copy_image(&dir.join("a/pic.png"), &images_dir).unwrap();
+        let b = copy_image(&dir.join("b/pic.png"), &images_dir).unwrap();
+        let c = copy_image(&dir.join("c/pic.png"), &images_dir).unwrap();
+        assert_eq!(a, hash::hashed_file_name("pic.png", b"a"));
+        assert_ne!(a, b);
+        assert_eq!(a, c);
+        assert_eq!(std::fs::read_dir(&images_dir).unwrap().count(), 2);
+        assert_eq!(std::fs::read(images_dir.join(&b)).unwrap(), b"b");
+
+        remove_unreferenced_images(&images_dir, &[b.clone()].iter().cloned().collect());
+        assert!(!images_dir.join(&a).exists());
+        assert!(images_dir.join(&b).exists());
+    }
+
+    fn write_xml(xml_dir: &Path, ref_id: &str, kind: &str, content: &str) {
+        std::fs::write(
+            xml_dir.join(format!("{}.xml", ref_id)),
+            format!(
+                "<doxygen><compounddef id=\"{}\" kind=\"{}\">{}</compounddef></doxygen>",
+                ref_id, kind, content
+            ),
+        )
+        .unwrap();
+    }
+
+    /// A project with a page `guide` and its subpage `sub`, and a header `a.h` declaring
+    /// namespace `ns` with a function `f`. The guide links to `ns`, to a missing ref and shows `docs/pic.png`.
+    fn write_project(name: &str, config: &str) -> (PathBuf, Settings) {
+        let dir = test_dir(name);
+        let xml_dir = dir.join("xml");
+        std::fs::create_dir_all(&xml_dir).unwrap();
+        std::fs::write(
+            xml_dir.join("index.xml"),
+            "<doxygenindex><compound refid=\"guide\" kind=\"page\"/>\
+             <compound refid=\"sub\" kind=\"page\"/><compound refid=\"a_8h\" kind=\"file\"/>\
+             <compound refid=\"namespacens\" kind=\"namespace\"/></doxygenindex>",
+        )
+        .unwrap();
+        write_xml(
+            &xml_dir,
+            "guide",
+            "page",
+            "<compoundname>guide</compoundname><title>Guide</title>\
+             <innerpage refid=\"sub\">sub</innerpage><detaileddescription><para>\
+             See <ref refid=\"namespacens\" kindref=\"compound\">ns</ref> and \
+             <ref refid=\"missing\" kindref=\"compound\">gone</ref>.\
+             <image type=\"html\" name=\"pic.png\"></image></para></detaileddescription>\
+             <location file=\"docs/guide.md\"/>",
+        );
+        write_xml(
+            &xml_dir,
+            "sub",
+            "page",
+            "<compoundname>sub</compoundname><title>Sub</title>",
+        );
+        write_xml(
+            &xml_dir,
+            "a_8h",
+            "file",
+            "<compoundname>a.h</compoundname>\
+             <innernamespace refid=\"namespacens\">ns</innernamespace>\
+             <location file=\"include/a.h\"/>",
+        );
+        write_xml(
+            &xml_dir,
+            "namespacens",
+            "namespace",
+            "<compoundname>ns</compoundname><sectiondef kind=\"func\">\
+             <memberdef kind=\"function\" id=\"namespacens_1f\" prot=\"public\">\
+             <type>void</type><name>f</name><argsstring>()</argsstring>\
+             <briefdescription><para>Does f.</para></briefdescription>\
+             <detaileddescription><para>More.</para></detaileddescription>\
+             <location file=\"include/a.h\"/></memberdef></sectiondef>\
+             <location file=\"include/a.h\"/>",
+        );
+        std::fs::create_dir_all(dir.join("src/docs")).unwrap();
+        std::fs::write(dir.join("src/docs/pic.png"), "picture").unwrap();
+
+        let config_file = dir.join("doxyrs.toml");
+        std::fs::write(
+            &config_file,
+            format!(
+                "[input]\nsource = \"src\"\nxml = \"xml\"\n[output]\ndir = \"html\"\n{}",
+                config
+            ),
+        )
+        .unwrap();
+        let cli = Cli::parse_from(["doxyrs", "--config", config_file.to_str().unwrap()]);
+        (dir, load_settings(&cli).unwrap())
+    }
+
+    #[test]
+    fn projects_without_a_mainpage_get_an_overview() {
+        let (dir, settings) = write_project("overview", "");
+        build(&settings);
+        let index = std::fs::read_to_string(dir.join("html/index.html")).unwrap();
+        assert!(index.contains("<title>Overview</title>"));
+        // subpages are reached through their parent
+        assert!(index.contains("<a href=\"guide.html\">Guide</a>"));
+        assert!(!index.contains("Sub"));
+        assert!(index.contains("<a href=\"a_8h.html#namespacens\">ns</a>"));
+        assert!(index.contains("<a href=\"a_8h.html\">a.h</a>"));
+    }
+
+    #[test]
+    fn the_mainpage_becomes_index_html() {
+        let (dir, settings) = write_project("mainpage", "");
+        let index_path = dir.join("xml/index.xml");
+        let index = std::fs::read_to_string(&index_path).unwrap().replace(
+            "</doxygenindex>",
+            "<compound refid=\"indexpage\" kind=\"page\"/></doxygenindex>",
+        );
+        std::fs::write(&index_path, index).unwrap();
+        write_xml(
+            &dir.join("xml"),
+            "indexpage",
+            "page",
+            "<compoundname>index</compoundname><title>Home</title>",
+        );
+        build(&settings);
+        let index = std::fs::read_to_string(dir.join("html/index.html")).unwrap();
+        assert!(index.contains("<title>Home</title>"));
+        assert!(!index.contains("Overview"));
+        assert!(!dir.join("html/indexpage.html").exists());
+    }
+
+    #[test]
+    fn broken_links_are_written_as_json_and_can_fail_the_build() {
+        let (_, settings) = write_project("broken-links", "");
+        assert!(build(&settings));
+
+        let config = "[check]\nfail_on_broken_links = true\nbroken_links_json = \"broken.json\"\n";
+        let (dir, settings) = write_project("broken-links-fail", config);
+        assert!(!build(&settings));
+        let report = std::fs::read_to_string(dir.join("broken.json")).unwrap();
+        let report = json::parse(&report).unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0]["page"], "guide.html");
+        assert_eq!(report[0]["source"], "docs/guide.md");
+        assert_eq!(report[0]["ref_id"], "missing");
+        assert_eq!(report[0]["text"], "gone");
+        let guide = std::fs::read_to_string(dir.join("html/guide.html")).unwrap();
+        assert!(guide.contains("<a class=\"broken-link\">gone</a>"));
+
+        // skipped pages still report their broken links
+        assert!(!build(&settings));
+        let again = std::fs::read_to_string(dir.join("broken.json")).unwrap();
+        assert_eq!(json::parse(&again).unwrap(), report);
+    }
+
+    #[test]
+    fn changed_images_replace_the_old_copy() {
+        let (dir, settings) = write_project("images", "");
+        build(&settings);
+        let old_name = hash::hashed_file_name("pic.png", b"picture");
+        let guide = std::fs::read_to_string(dir.join("html/guide.html")).unwrap();
+        assert!(guide.contains(&format!("<img src=\"images/{}\"", old_name)));
+
+        std::fs::write(dir.join("src/docs/pic.png"), "new picture").unwrap();
+        build(&settings);
+        let new_name = hash::hashed_file_name("pic.png", b"new picture");
+        let guide = std::fs::read_to_string(dir.join("html/guide.html")).unwrap();
+        assert!(guide.contains(&format!("<img src=\"images/{}\"", new_name)));
+        assert!(dir.join("html/images").join(new_name).exists());
+        assert!(!dir.join("html/images").join(old_name).exists());
+    }
+
+    #[test]
+    fn unchanged_pages_are_skipped() {
+        let (dir, settings) = write_project("incremental", "");
+        build(&settings);
+        let guide_path = dir.join("html/guide.html");
+        let file_path = dir.join("html/a_8h.html");
+        std::fs::write(&guide_path, "skipped").unwrap();
+        std::fs::write(&file_path, "skipped").unwrap();
+        build(&settings);
+        assert_eq!(std::fs::read_to_string(&guide_path).unwrap(), "skipped");
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "skipped");
+
+        // the guide links to ns, whose tooltip shows its kind and qualified name
+        let xml_path = dir.join("xml/namespacens.xml");
+        let xml = std::fs::read_to_string(&xml_path).unwrap();
+        std::fs::write(&xml_path, xml.replace(">ns<", ">ns2<")).unwrap();
+        build(&settings);
+        assert!(std::fs::read_to_string(&guide_path)
+            .unwrap()
+            .contains("title=\"namespace ns2\""));
+        assert_ne!(std::fs::read_to_string(&file_path).unwrap(), "skipped");
+
+        std::fs::write(&guide_path, "skipped").unwrap();
+        let full_rebuild = Settings {
+            full_rebuild: true,
+            ..settings
+        };
+        build(&full_rebuild);
+        assert_ne!(std::fs::read_to_string(&guide_path).unwrap(), "skipped");
+    }
+
+    #[test]
+    fn pages_of_removed_compounds_are_deleted() {
+        let (dir, settings) = write_project("stale-pages", "");
+        build(&settings);
+        assert!(dir.join("html/sub.html").exists());
+        let index_path = dir.join("xml/index.xml");
+        let index = std::fs::read_to_string(&index_path)
+            .unwrap()
+            .replace("<compound refid=\"sub\" kind=\"page\"/>", "");
+        std::fs::write(&index_path, index).unwrap();
+        build(&settings);
+        assert!(!dir.join("html/sub.html").exists());
+        assert!(dir.join("html/guide.html").exists());
+    }
+
+    #[test]
+    fn summary_layout_lists_members_with_their_briefs() {
+        let (dir, settings) = write_project("summary", "member_layout = \"summary\"\n");
+        build(&settings);
+        let html = std::fs::read_to_string(dir.join("html/a_8h.html")).unwrap();
+        let start = html.find("<table class=\"member-summary\">").unwrap();
+        let end = start + html[start..].find("</table>").unwrap();
+        let (summary, details) = (&html[start..end], &html[end..]);
+        assert!(summary.contains("<td class=\"member-kind\">function</td>"));
+        assert!(summary.contains("<a href=\"#namespacens_1f\">f</a>"));
+        assert!(summary.contains("<td class=\"brief\"><p>Does f.</p></td>"));
+        assert!(!summary.contains("More."));
+        assert!(details.contains("<a name=\"namespacens_1f\"></a>"));
+        assert!(details.contains("More."));
+
+        let (dir, settings) = write_project("detailed", "");
+        build(&settings);
+        let html = std::fs::read_to_string(dir.join("html/a_8h.html")).unwrap();
+        assert!(!html.contains("member-summary"));
+        assert!(html.contains("More."));
+    }
+
+    #[test]
+    fn tooltips_show_kind_qualified_signature_and_brief() {
+        assert_eq!(tooltip("class A".to_owned(), ""), "class A");
+        assert_eq!(
+            tooltip("class A".to_owned(), "<p>Is <b>bold</b>.</p>"),
+            "class A\nIs bold."
+        );
+
+        let (dir, settings) = write_project("tooltips", "");
+        let xml_dir = dir.join("xml");
+        let compounds = vec![
+            Compound::File(
+                parser::parse_compound_file(&xml_dir, "a_8h", &settings.options).unwrap(),
+            ),
+            Compound::Page(
+                parser::parse_compound_page(&xml_dir, "guide", &settings.options).unwrap(),
+            ),
+        ];
+        let (ref_to_path, ref_to_tooltip) = create_ref_to_path_map(&compounds);
+        assert_eq!(ref_to_path["namespacens"], "a_8h.html#namespacens");
+        assert_eq!(ref_to_path["namespacens_1f"], "a_8h.html#namespacens_1f");
+        assert_eq!(ref_to_tooltip["a_8h"], "file a.h");
+        assert_eq!(ref_to_tooltip["namespacens"], "namespace ns");
+        assert_eq!(
+            ref_to_tooltip["namespacens_1f"],
+            "function ns::f() → void\nDoes f."
+        );
+        assert_eq!(ref_to_tooltip["guide"], "Guide");
+
+        let options = parser::ParseOptions {
+            inline_scopes: false,
+            ..settings.options
+        };
+        let scope_page = parser::parse_compound_scope_page(&xml_dir, "namespacens", &options);
+        let compounds = vec![Compound::Scope(Box::new(scope_page.unwrap()))];
+        let (ref_to_path, ref_to_tooltip) = create_ref_to_path_map(&compounds);
+        assert_eq!(ref_to_path["namespacens"], "namespacens.html");
+        assert_eq!(
+            ref_to_path["namespacens_1f"],
+            "namespacens.html#namespacens_1f"
+        );
+        assert_eq!(
+            ref_to_tooltip["namespacens_1f"],
+            "function ns::f() → void\nDoes f."
+        );
+    }
 }
@@ -0,0 +1,968 @@
+use serde::Deserialize;
+
+// Converts the TeX of doxygen formulas to MathML at build time, so pages need no script (and no
+// network access) to show math. Browsers render MathML natively.
+//
+// This covers the subset of LaTeX math that API docs use in practice: scripts, fractions, roots,
+// fences, Greek letters, common symbols and functions, accents, fonts, text and spacing. Anything
+// else is an error, and the formula is shown as its source instead.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MathBackend {
+    /// Convert formulas to MathML while building
+    #[default]
+    Mathml,
+    /// Keep the TeX source in `\( ... \)` for a renderer that the theme includes
+    Tex,
+}
+
+/// Doxygen writes `\f$x\f$` as `$x$`, `\f(x\f)` as `\(x\)`, `\f[x\f]` as `\[x\]` and
+/// `\f{env}{x\f}` as `\begin{env}x\end{env}`. Returns the TeX without the delimiters, and whether
+/// it is display math. Environments are kept whole.
+pub fn split_formula(formula: &str) -> (&str, bool) {
+    let formula = formula.trim();
+    if let Some(tex) = formula
+        .strip_prefix("\\[")
+        .and_then(|f| f.strip_suffix("\\]"))
+    {
+        (tex, true)
+    } else if let Some(tex) = formula
+        .strip_prefix("\\(")
+        .and_then(|f| f.strip_suffix("\\)"))
+    {
+        (tex, false)
+    } else if formula.starts_with("\\begin") {
+        (formula, true)
+    } else {
+        (formula.trim_matches('$'), false)
+    }
+}
+
+/// Renders `tex` as a `<math>` element.
+pub fn to_mathml(tex: &str, display: bool) -> Result<String, String> {
+    let tokens = tokenize(tex)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        display,
+        variant: None,
+    };
+    let row = parser.parse_row(End::Input)?;
+    Ok(format!(
+        "<math display=\"{}\"><semantics><mrow>{}</mrow><annotation encoding=\"application/x-tex\">{}</annotation></semantics></math>",
+        if display { "block" } else { "inline" },
+        row,
+        tera::escape_html(tex.trim())
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// `\name`, or `\` followed by a single non-letter like `\,`
+    Command(String),
+    Letter(char),
+    Number(String),
+    /// Any other character, including `{`, `}`, `^`, `_`, `&`
+    Char(char),
+    Space,
+}
+
+fn tokenize(tex: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = tex.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let mut name = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if !c.is_ascii_alphabetic() {
+                            break;
+                        }
+                        name.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token::Command(name));
+                }
+                Some(c) => tokens.push(Token::Command(c.to_string())),
+                None => return Err("formula ends with a backslash".to_owned()),
+            },
+            c if c.is_whitespace() => {
+                if tokens.last() != Some(&Token::Space) {
+                    tokens.push(Token::Space);
+                }
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_digit() && c != '.' {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() => tokens.push(Token::Letter(c)),
+            c => tokens.push(Token::Char(c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// What ends the row being parsed.
+#[derive(Clone, Copy, PartialEq)]
+enum End {
+    Input,
+    Brace,
+    Bracket,
+    Right,
+    /// A cell of an environment, ended by `&`, `\\` or `\end`, which are left for the caller
+    Cell,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    display: bool,
+    /// Font selected by `\mathbf` and friends
+    variant: Option<Variant>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Variant {
+    Normal,
+    Bold,
+    DoubleStruck,
+    Script,
+    SansSerif,
+    Monospace,
+}
+
+/// How an atom takes its sub- and superscripts.
+#[derive(Clone, Copy, PartialEq)]
+enum Limits {
+    Scripts,
+    /// Above and below in display mode, like `\sum` and `\lim`
+    Display,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(&Token::Space) {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_row(&mut self, end: End) -> Result<String, String> {
+        let mut row = String::new();
+        loop {
+            self.skip_spaces();
+            match (self.peek(), end) {
+                // the environment reports its missing \end
+                (None, End::Input | End::Cell) => return Ok(row),
+                (None, _) => return Err("missing closing brace or \\right".to_owned()),
+                (Some(Token::Char('}')), End::Brace) => {
+                    self.pos += 1;
+                    return Ok(row);
+                }
+                (Some(Token::Char(']')), End::Bracket) => {
+                    self.pos += 1;
+                    return Ok(row);
+                }
+                (Some(Token::Command(name)), End::Right) if name == "right" => {
+                    self.pos += 1;
+                    return Ok(row);
+                }
+                (Some(Token::Char('&')), End::Cell) => return Ok(row),
+                (Some(Token::Command(name)), End::Cell) if name == "\\" || name == "end" => {
+                    return Ok(row)
+                }
+                (Some(Token::Char('}')), _) => return Err("unbalanced '}'".to_owned()),
+                (Some(Token::Command(name)), _) if name == "right" => {
+                    return Err("\\right without \\left".to_owned())
+                }
+                _ => row.push_str(&self.parse_scripted()?),
+            }
+        }
+    }
+
+    /// An atom followed by any number of `^` and `_` scripts.
+    fn parse_scripted(&mut self) -> Result<String, String> {
+        let (base, limits) = match self.peek() {
+            Some(Token::Char('^')) | Some(Token::Char('_')) => {
+                ("<mrow></mrow>".to_owned(), Limits::Scripts)
+            }
+            _ => self.parse_atom()?,
+        };
+        let mut sub = None;
+        let mut sup = None;
+        // primes are superscripts too, a following `^` adds to them: x'^2 is x′², as in TeX
+        let mut primes = String::new();
+        loop {
+            self.skip_spaces();
+            let slot = match self.peek() {
+                Some(Token::Char('_')) => &mut sub,
+                Some(Token::Char('^')) => &mut sup,
+                Some(Token::Char('\'')) if sup.is_none() => {
+                    self.pos += 1;
+                    primes.push_str("<mo>′</mo>");
+                    continue;
+                }
+                Some(Token::Char('\'')) => return Err("double subscript or superscript".to_owned()),
+                _ => break,
+            };
+            if slot.is_some() {
+                return Err("double subscript or superscript".to_owned());
+            }
+            self.pos += 1;
+            self.skip_spaces();
+            let (script, _) = self.parse_atom()?;
+            *slot = Some(script);
+        }
+        let sup = match sup {
+            Some(sup) => Some(primes + &sup),
+            None if !primes.is_empty() => Some(primes),
+            None => None,
+        };
+
+        let under_over = limits == Limits::Display && self.display;
+        let wrap = |s: String| format!("<mrow>{}</mrow>", s);
+        Ok(match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) if under_over => format!("<munder>{}{}</munder>", base, wrap(sub)),
+            (Some(sub), None) => format!("<msub>{}{}</msub>", base, wrap(sub)),
+            (None, Some(sup)) if under_over => format!("<mover>{}{}</mover>", base, wrap(sup)),
+            (None, Some(sup)) => format!("<msup>{}{}</msup>", base, wrap(sup)),
+            (Some(sub), Some(sup)) if under_over => format!(
+                "<munderover>{}{}{}</munderover>",
+                base,
+                wrap(sub),
+                wrap(sup)
+            ),
+            (Some(sub), Some(sup)) => {
+                format!("<msubsup>{}{}{}</msubsup>", base, wrap(sub), wrap(sup))
+            }
+        })
+    }
+
+    /// A single element: a character, a group or a command with its arguments.
+    fn parse_atom(&mut self) -> Result<(String, Limits), String> {
+        self.skip_spaces();
+        let token = self.next().ok_or("missing argument")?;
+        let element = match token {
+            Token::Letter(c) => self.identifier(c),
+            Token::Number(n) => format!("<mn>{}</mn>", n),
+            Token::Char('{') => format!("<mrow>{}</mrow>", self.parse_row(End::Brace)?),
+            Token::Char(c @ ('^' | '_' | '&' | '}' | '#' | '$' | '%' | '~')) => {
+                return Err(format!("unexpected '{}'", c))
+            }
+            Token::Char('-') => "<mo>−</mo>".to_owned(),
+            Token::Char('*') => "<mo>∗</mo>".to_owned(),
+            Token::Char('\'') => "<mo>′</mo>".to_owned(),
+            Token::Char(c) => format!("<mo>{}</mo>", tera::escape_html(&c.to_string())),
+            Token::Space => unreachable!("spaces are skipped"),
+            Token::Command(name) => return self.parse_command(&name),
+        };
+        Ok((element, Limits::Scripts))
+    }
+
+    fn identifier(&self, c: char) -> String {
+        match self.variant {
+            None => format!("<mi>{}</mi>", c),
+            Some(Variant::Normal) => format!("<mi mathvariant=\"normal\">{}</mi>", c),
+            Some(variant) => format!("<mi>{}</mi>", styled_char(c, variant)),
+        }
+    }
+
+    /// The argument of a command: one token or a braced group.
+    fn parse_argument(&mut self) -> Result<String, String> {
+        let (element, _) = self.parse_atom()?;
+        Ok(element)
+    }
+
+    /// The raw text of a braced argument, for `\text` and `\operatorname`.
+    fn parse_text_argument(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        if self.next() != Some(Token::Char('{')) {
+            return Err("expected '{'".to_owned());
+        }
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            match self.next().ok_or("missing closing brace")? {
+                Token::Char('}') if depth == 0 => return Ok(text),
+                Token::Char('}') => {
+                    depth -= 1;
+                    text.push('}');
+                }
+                Token::Char('{') => {
+                    depth += 1;
+                    text.push('{');
+                }
+                Token::Char(c) | Token::Letter(c) => text.push(c),
+                Token::Number(n) => text.push_str(&n),
+                Token::Space => text.push(' '),
+                Token::Command(name) => text.push_str(&name),
+            }
+        }
+    }
+
+    fn parse_delimiter(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        let delimiter = match self.next().ok_or("missing delimiter")? {
+            Token::Char('.') => return Ok(String::new()),
+            Token::Char(c) => c.to_string(),
+            Token::Command(name) => match name.as_str() {
+                "{" | "lbrace" => "{".to_owned(),
+                "}" | "rbrace" => "}".to_owned(),
+                "|" | "Vert" => "‖".to_owned(),
+                "langle" => "⟨".to_owned(),
+                "rangle" => "⟩".to_owned(),
+                "lfloor" => "⌊".to_owned(),
+                "rfloor" => "⌋".to_owned(),
+                "lceil" => "⌈".to_owned(),
+                "rceil" => "⌉".to_owned(),
+                "vert" => "|".to_owned(),
+                _ => return Err(format!("unknown delimiter \\{}", name)),
+            },
+            token => return Err(format!("unknown delimiter {:?}", token)),
+        };
+        Ok(format!(
+            "<mo fence=\"true\" stretchy=\"true\">{}</mo>",
+            tera::escape_html(&delimiter)
+        ))
+    }
+
+    fn with_variant(&mut self, variant: Variant) -> Result<String, String> {
+        let outer = self.variant.replace(variant);
+        let result = self.parse_argument();
+        self.variant = outer;
+        result
+    }
+
+    fn parse_command(&mut self, name: &str) -> Result<(String, Limits), String> {
+        if let Some(c) = greek(name) {
+            let element = if c.is_uppercase() {
+                format!("<mi mathvariant=\"normal\">{}</mi>", c)
+            } else {
+                format!("<mi>{}</mi>", c)
+            };
+            return Ok((element, Limits::Scripts));
+        }
+        if let Some(c) = big_operator(name) {
+            let limits = if name.contains("int") {
+                Limits::Scripts
+            } else {
+                Limits::Display
+            };
+            return Ok((format!("<mo largeop=\"true\">{}</mo>", c), limits));
+        }
+        if let Some(c) = symbol(name) {
+            return Ok((
+                format!("<mo>{}</mo>", tera::escape_html(c)),
+                Limits::Scripts,
+            ));
+        }
+        if FUNCTIONS.contains(&name) {
+            return Ok((format!("<mi>{}</mi>", name), Limits::Scripts));
+        }
+        if LIMIT_FUNCTIONS.contains(&name) {
+            let text = match name {
+                "limsup" => "lim sup",
+                "liminf" => "lim inf",
+                _ => name,
+            };
+            return Ok((format!("<mi>{}</mi>", text), Limits::Display));
+        }
+
+        let element = match name {
+            "begin" => {
+                let environment = self.parse_text_argument()?;
+                self.parse_environment(&environment)?
+            }
+            "\\" => return Err("line break outside of an environment".to_owned()),
+            "end" => return Err("\\end without \\begin".to_owned()),
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.parse_argument()?;
+                let denominator = self.parse_argument()?;
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "binom" => {
+                let top = self.parse_argument()?;
+                let bottom = self.parse_argument()?;
+                format!(
+                    "<mrow><mo>(</mo><mfrac linethickness=\"0\">{}{}</mfrac><mo>)</mo></mrow>",
+                    top, bottom
+                )
+            }
+            "sqrt" => {
+                self.skip_spaces();
+                if self.peek() == Some(&Token::Char('[')) {
+                    self.pos += 1;
+                    let index = self.parse_row(End::Bracket)?;
+                    let radicand = self.parse_argument()?;
+                    format!("<mroot>{}<mrow>{}</mrow></mroot>", radicand, index)
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_argument()?)
+                }
+            }
+            "left" => {
+                let open = self.parse_delimiter()?;
+                let content = self.parse_row(End::Right)?;
+                let close = self.parse_delimiter()?;
+                format!("<mrow>{}{}{}</mrow>", open, content, close)
+            }
+            "bigl" | "bigr" | "Bigl" | "Bigr" | "big" | "Big" | "biggl" | "biggr" => {
+                self.parse_delimiter()?
+            }
+            "text" | "textrm" | "mbox" | "textit" | "textbf" => {
+                format!(
+                    "<mtext>{}</mtext>",
+                    tera::escape_html(&self.parse_text_argument()?)
+                )
+            }
+            "operatorname" => format!(
+                "<mi>{}</mi>",
+                tera::escape_html(&self.parse_text_argument()?)
+            ),
+            "mathrm" => self.with_variant(Variant::Normal)?,
+            "mathbf" | "boldsymbol" | "bm" => self.with_variant(Variant::Bold)?,
+            "mathbb" => self.with_variant(Variant::DoubleStruck)?,
+            "mathcal" | "mathscr" => self.with_variant(Variant::Script)?,
+            "mathsf" => self.with_variant(Variant::SansSerif)?,
+            "mathtt" => self.with_variant(Variant::Monospace)?,
+            "mathit" => {
+                let outer = self.variant.take();
+                let result = self.parse_argument();
+                self.variant = outer;
+                result?
+            }
+            "hat" | "widehat" | "bar" | "overline" | "vec" | "dot" | "ddot" | "tilde"
+            | "widetilde" | "check" | "breve" | "acute" | "grave" => {
+                let accent = match name {
+                    "hat" | "widehat" => "^",
+                    "bar" | "overline" => "¯",
+                    "vec" => "→",
+                    "dot" => "˙",
+                    "ddot" => "¨",
+                    "tilde" | "widetilde" => "~",
+                    "check" => "ˇ",
+                    "breve" => "˘",
+                    "acute" => "´",
+                    _ => "`",
+                };
+                let stretchy = matches!(name, "widehat" | "overline" | "widetilde");
+                format!(
+                    "<mover accent=\"true\">{}<mo stretchy=\"{}\">{}</mo></mover>",
+                    self.parse_argument()?,
+                    stretchy,
+                    accent
+                )
+            }
+            "underline" => format!(
+                "<munder accentunder=\"true\">{}<mo stretchy=\"true\">_</mo></munder>",
+                self.parse_argument()?
+            ),
+            "overbrace" | "underbrace" => {
+                let content = self.parse_argument()?;
+                if name == "overbrace" {
+                    format!("<mover>{}<mo stretchy=\"true\">⏞</mo></mover>", content)
+                } else {
+                    format!("<munder>{}<mo stretchy=\"true\">⏟</mo></munder>", content)
+                }
+            }
+            "," | "thinspace" => "<mspace width=\"0.1667em\"></mspace>".to_owned(),
+            ":" | ">" | "medspace" => "<mspace width=\"0.2222em\"></mspace>".to_owned(),
+            ";" | "thickspace" => "<mspace width=\"0.2778em\"></mspace>".to_owned(),
+            " " => "<mspace width=\"0.25em\"></mspace>".to_owned(),
+            "quad" => "<mspace width=\"1em\"></mspace>".to_owned(),
+            "qquad" => "<mspace width=\"2em\"></mspace>".to_owned(),
+            "!" | "negthinspace" => "<mspace width=\"-0.1667em\"></mspace>".to_owned(),
+            "{" | "lbrace" => "<mo>{</mo>".to_owned(),
+            "}" | "rbrace" => "<mo>}</mo>".to_owned(),
+            "|" => "<mo>‖</mo>".to_owned(),
+            "_" | "%" | "$" | "#" => format!("<mi>{}</mi>", name),
+            "&" => "<mo>&amp;</mo>".to_owned(),
+            // only affect spacing and sizes, which the browser decides
+            "limits" | "nolimits" | "displaystyle" | "textstyle" | "scriptstyle" | "nonumber"
+            | "notag" => String::new(),
+            _ => return Err(format!("unsupported command \\{}", name)),
+        };
+        Ok((element, Limits::Scripts))
+    }
+}
+
+/// Column alignments of an environment, repeated for more columns.
+enum Columns {
+    Centered,
+    Left,
+    /// `align` and friends: right-aligned before each `&`, left-aligned after
+    RightLeft,
+    /// From the column spec of `array`
+    Spec(Vec<&'static str>),
+}
+
+impl Parser {
+    /// Everything after `\begin{name}`, up to and including the matching `\end`.
+    fn parse_environment(&mut self, name: &str) -> Result<String, String> {
+        let base_name = name.trim_end_matches('*');
+        let (open, close, columns) = match base_name {
+            "matrix" | "smallmatrix" => ("", "", Columns::Centered),
+            "pmatrix" => ("(", ")", Columns::Centered),
+            "bmatrix" => ("[", "]", Columns::Centered),
+            "Bmatrix" => ("{", "}", Columns::Centered),
+            "vmatrix" => ("|", "|", Columns::Centered),
+            "Vmatrix" => ("‖", "‖", Columns::Centered),
+            "cases" => ("{", "", Columns::Left),
+            "align" | "aligned" | "alignat" | "alignedat" | "eqnarray" | "split" | "flalign" => {
+                if matches!(base_name, "alignat" | "alignedat") {
+                    // the number of columns
+                    self.parse_text_argument()?;
+                }
+                ("", "", Columns::RightLeft)
+            }
+            "gather" | "gathered" | "multline" | "equation" => ("", "", Columns::Centered),
+            "array" => {
+                let spec = self.parse_text_argument()?;
+                let columns = spec
+                    .chars()
+                    .filter_map(|c| match c {
+                        'l' => Some("left"),
+                        'c' => Some("center"),
+                        'r' => Some("right"),
+                        _ => None,
+                    })
+                    .collect();
+                ("", "", Columns::Spec(columns))
+            }
+            _ => return Err(format!("unsupported environment {}", name)),
+        };
+
+        let mut rows: Vec<Vec<String>> = vec![];
+        let mut cells = vec![];
+        loop {
+            cells.push(self.parse_row(End::Cell)?);
+            match self.next() {
+                Some(Token::Char('&')) => {}
+                Some(Token::Command(command)) if command == "\\" => {
+                    rows.push(std::mem::take(&mut cells));
+                    // a spacing like \\[2pt] is ignored
+                    self.skip_spaces();
+                    if self.peek() == Some(&Token::Char('[')) {
+                        while !matches!(self.next(), Some(Token::Char(']')) | None) {}
+                    }
+                }
+                Some(Token::Command(command)) if command == "end" => {
+                    let end_name = self.parse_text_argument()?;
+                    if end_name != name {
+                        return Err(format!(
+                            "\\begin{{{}}} ended by \\end{{{}}}",
+                            name, end_name
+                        ));
+                    }
+                    rows.push(cells);
+                    break;
+                }
+                _ => return Err(format!("missing \\end{{{}}}", name)),
+            }
+        }
+        // a trailing \\ does not start another row
+        if rows.len() > 1 && rows.last().is_some_and(|r| r.len() == 1 && r[0].is_empty()) {
+            rows.pop();
+        }
+
+        let mut table = format!(
+            "<mtable{}>",
+            if self.display && base_name != "smallmatrix" {
+                " displaystyle=\"true\""
+            } else {
+                ""
+            }
+        );
+        for row in rows {
+            table.push_str("<mtr>");
+            for (i, cell) in row.into_iter().enumerate() {
+                let align = match &columns {
+                    Columns::Centered => "center",
+                    Columns::Left => "left",
+                    Columns::RightLeft if i % 2 == 0 => "right",
+                    Columns::RightLeft => "left",
+                    Columns::Spec(spec) => spec.get(i).copied().unwrap_or("center"),
+                };
+                table.push_str(&format!(
+                    "<mtd columnalign=\"{}\"><mrow>{}</mrow></mtd>",
+                    align, cell
+                ));
+            }
+            table.push_str("</mtr>");
+        }
+        table.push_str("</mtable>");
+
+        let fence = |delimiter: &str| {
+            if delimiter.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "<mo fence=\"true\" stretchy=\"true\">{}</mo>",
+                    tera::escape_html(delimiter)
+                )
+            }
+        };
+        Ok(format!(
+            "<mrow>{}{}{}</mrow>",
+            fence(open),
+            table,
+            fence(close)
+        ))
+    }
+}
+
+fn greek(name: &str) -> Option<char> {
+    Some(match name {
+        "alpha" => 'α',
+        "beta" => 'β',
+        "gamma" => 'γ',
+        "delta" => 'δ',
+        "epsilon" => 'ϵ',
+        "varepsilon" => 'ε',
+        "zeta" => 'ζ',
+        "eta" => 'η',
+        "theta" => 'θ',
+        "vartheta" => 'ϑ',
+        "iota" => 'ι',
+        "kappa" => 'κ',
+        "lambda" => 'λ',
+        "mu" => 'μ',
+        "nu" => 'ν',
+        "xi" => 'ξ',
+        "pi" => 'π',
+        "varpi" => 'ϖ',
+        "rho" => 'ρ',
+        "varrho" => 'ϱ',
+        "sigma" => 'σ',
+        "varsigma" => 'ς',
+        "tau" => 'τ',
+        "upsilon" => 'υ',
+        "phi" => 'ϕ',
+        "varphi" => 'φ',
+        "chi" => 'χ',
+        "psi" => 'ψ',
+        "omega" => 'ω',
+        "Gamma" => 'Γ',
+        "Delta" => 'Δ',
+        "Theta" => 'Θ',
+        "Lambda" => 'Λ',
+        "Xi" => 'Ξ',
+        "Pi" => 'Π',
+        "Sigma" => 'Σ',
+        "Upsilon" => 'Υ',
+        "Phi" => 'Φ',
+        "Psi" => 'Ψ',
+        "Omega" => 'Ω',
+        _ => return None,
+    })
+}
+
+fn big_operator(name: &str) -> Option<char> {
+    Some(match name {
+        "sum" => '∑',
+        "prod" => '∏',
+        "coprod" => '∐',
+        "int" => '∫',
+        "iint" => '∬',
+        "iiint" => '∭',
+        "oint" => '∮',
+        "bigcup" => '⋃',
+        "bigcap" => '⋂',
+        "bigoplus" => '⨁',
+        "bigotimes" => '⨂',
+        "bigvee" => '⋁',
+        "bigwedge" => '⋀',
+        _ => return None,
+    })
+}
+
+fn symbol(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "cdot" => "⋅",
+        "times" => "×",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "oplus" => "⊕",
+        "ominus" => "⊖",
+        "otimes" => "⊗",
+        "wedge" | "land" => "∧",
+        "vee" | "lor" => "∨",
+        "cap" => "∩",
+        "cup" => "∪",
+        "setminus" => "∖",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "ll" => "≪",
+        "gg" => "≫",
+        "approx" => "≈",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "equiv" => "≡",
+        "propto" => "∝",
+        "prec" => "≺",
+        "succ" => "≻",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "supset" => "⊃",
+        "subseteq" => "⊆",
+        "supseteq" => "⊇",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "mid" => "∣",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "uparrow" => "↑",
+        "downarrow" => "↓",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "emptyset" | "varnothing" => "∅",
+        "ell" => "ℓ",
+        "hbar" => "ℏ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "aleph" => "ℵ",
+        "angle" => "∠",
+        "degree" => "°",
+        "prime" => "′",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "vert" => "|",
+        "Vert" => "‖",
+        "colon" => ":",
+        _ => return None,
+    })
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "coth", "log", "ln", "lg", "exp", "det", "dim", "ker", "hom", "arg", "deg", "gcd", "Pr",
+];
+
+const LIMIT_FUNCTIONS: &[&str] = &["lim", "limsup", "liminf", "max", "min", "sup", "inf"];
+
+/// Maps letters and digits to the Mathematical Alphanumeric Symbols block, which renders in the
+/// right font without relying on `mathvariant` support.
+fn styled_char(c: char, variant: Variant) -> char {
+    let offset = |base: u32, start: char| char::from_u32(base + (c as u32 - start as u32));
+    let styled = match (variant, c) {
+        (Variant::DoubleStruck, 'C') => Some('ℂ'),
+        (Variant::DoubleStruck, 'H') => Some('ℍ'),
+        (Variant::DoubleStruck, 'N') => Some('ℕ'),
+        (Variant::DoubleStruck, 'P') => Some('ℙ'),
+        (Variant::DoubleStruck, 'Q') => Some('ℚ'),
+        (Variant::DoubleStruck, 'R') => Some('ℝ'),
+        (Variant::DoubleStruck, 'Z') => Some('ℤ'),
+        (Variant::Script, 'B') => Some('ℬ'),
+        (Variant::Script, 'E') => Some('ℰ'),
+        (Variant::Script, 'F') => Some('ℱ'),
+        (Variant::Script, 'H') => Some('ℋ'),
+        (Variant::Script, 'I') => Some('ℐ'),
+        (Variant::Script, 'L') => Some('ℒ'),
+        (Variant::Script, 'M') => Some('ℳ'),
+        (Variant::Script, 'R') => Some('ℛ'),
+        (Variant::Script, 'e') => Some('ℯ'),
+        (Variant::Script, 'g') => Some('ℊ'),
+        (Variant::Script, 'o') => Some('ℴ'),
+        (Variant::Bold, 'A'..='Z') => offset(0x1D400, 'A'),
+        (Variant::Bold, 'a'..='z') => offset(0x1D41A, 'a'),
+        (Variant::Bold, '0'..='9') => offset(0x1D7CE, '0'),
+        (Variant::DoubleStruck, 'A'..='Z') => offset(0x1D538, 'A'),
+        (Variant::DoubleStruck, 'a'..='z') => offset(0x1D552, 'a'),
+        (Variant::DoubleStruck, '0'..='9') => offset(0x1D7D8, '0'),
+        (Variant::Script, 'A'..='Z') => offset(0x1D49C, 'A'),
+        (Variant::Script, 'a'..='z') => offset(0x1D4B6, 'a'),
+        (Variant::SansSerif, 'A'..='Z') => offset(0x1D5A0, 'A'),
+        (Variant::SansSerif, 'a'..='z') => offset(0x1D5BA, 'a'),
+        (Variant::SansSerif, '0'..='9') => offset(0x1D7E2, '0'),
+        (Variant::Monospace, 'A'..='Z') => offset(0x1D670, 'A'),
+        (Variant::Monospace, 'a'..='z') => offset(0x1D68A, 'a'),
+        (Variant::Monospace, '0'..='9') => offset(0x1D7F6, '0'),
+        _ => None,
+    };
+    styled.unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rendered formula without the `<math>`, `<semantics>` and annotation wrapping.
+    fn render(tex: &str, display: bool) -> String {
+        let mathml = to_mathml(tex, display).unwrap();
+        let start = mathml.find("<semantics><mrow>").unwrap() + "<semantics><mrow>".len();
+        let end = mathml.rfind("</mrow><annotation").unwrap();
+        mathml[start..end].to_owned()
+    }
+
+    #[test]
+    fn split_inline_and_display() {
+        assert_eq!(split_formula("$x^2$"), ("x^2", false));
+        assert_eq!(split_formula("\\(x\\)"), ("x", false));
+        assert_eq!(split_formula(" \\[x + 1\\] "), ("x + 1", true));
+    }
+
+    #[test]
+    fn split_keeps_environments_whole() {
+        let formula = "\\begin{align}x &= 1\\end{align}";
+        assert_eq!(split_formula(formula), (formula, true));
+    }
+
+    #[test]
+    fn wraps_in_math_with_annotation() {
+        assert_eq!(
+            to_mathml("a<b", false).unwrap(),
+            "<math display=\"inline\"><semantics><mrow><mi>a</mi><mo>&lt;</mo><mi>b</mi></mrow>\
+             <annotation encoding=\"application/x-tex\">a&lt;b</annotation></semantics></math>"
+        );
+        assert!(to_mathml("x", true)
+            .unwrap()
+            .starts_with("<math display=\"block\">"));
+    }
+
+    #[test]
+    fn fractions_and_roots() {
+        assert_eq!(
+            render("\\frac{a}{b}", false),
+            "<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>"
+        );
+        assert_eq!(
+            render("\\sqrt{x}", false),
+            "<msqrt><mrow><mi>x</mi></mrow></msqrt>"
+        );
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(
+            render("x_i^2", false),
+            "<msubsup><mi>x</mi><mrow><mi>i</mi></mrow><mrow><mn>2</mn></mrow></msubsup>"
+        );
+        assert!(to_mathml("x_1_2", false).is_err());
+    }
+
+    #[test]
+    fn primes_merge_with_a_following_superscript() {
+        assert_eq!(
+            render("x'^2", false),
+            "<msup><mi>x</mi><mrow><mo>′</mo><mn>2</mn></mrow></msup>"
+        );
+        assert_eq!(
+            render("f''_n", false),
+            "<msubsup><mi>f</mi><mrow><mi>n</mi></mrow><mrow><mo>′</mo><mo>′</mo></mrow></msubsup>"
+        );
+        assert!(to_mathml("x^2'", false).is_err());
+    }
+
+    #[test]
+    fn big_operator_limits_depend_on_display() {
+        assert!(
+            render("\\sum_{i=0}^n i", true).starts_with("<munderover><mo largeop=\"true\">∑</mo>")
+        );
+        assert!(render("\\sum_{i=0}^n i", false).starts_with("<msubsup>"));
+    }
+
+    #[test]
+    fn left_right() {
+        assert_eq!(
+            render("\\left( x \\right)", false),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mi>x</mi>\
+             <mo fence=\"true\" stretchy=\"true\">)</mo></mrow>"
+        );
+        assert_eq!(
+            to_mathml("\\left( x", false),
+            Err("missing closing brace or \\right".to_owned())
+        );
+        assert_eq!(
+            to_mathml("x \\right)", false),
+            Err("\\right without \\left".to_owned())
+        );
+    }
+
+    #[test]
+    fn matrices() {
+        let mathml = render("\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}", true);
+        assert!(mathml.starts_with("<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mtable"));
+        assert_eq!(mathml.matches("<mtr>").count(), 2);
+        assert_eq!(mathml.matches("<mtd columnalign=\"center\">").count(), 4);
+    }
+
+    #[test]
+    fn align_alternates_column_alignment() {
+        let mathml = render("\\begin{align} x &= 1 \\\\ y &= 2 \\\\ \\end{align}", true);
+        assert_eq!(mathml.matches("<mtr>").count(), 2);
+        assert_eq!(mathml.matches("<mtd columnalign=\"right\">").count(), 2);
+        assert_eq!(mathml.matches("<mtd columnalign=\"left\">").count(), 2);
+    }
+
+    #[test]
+    fn unknown_commands_are_errors() {
+        assert_eq!(
+            to_mathml("\\foo", false),
+            Err("unsupported command \\foo".to_owned())
+        );
+        assert_eq!(
+            to_mathml("\\begin{foo} x \\end{foo}", true),
+            Err("unsupported environment foo".to_owned())
+        );
+    }
+
+    #[test]
+    fn mismatched_environments_are_errors() {
+        assert_eq!(
+            to_mathml("\\begin{matrix} a \\end{cases}", true),
+            Err("\\begin{matrix} ended by \\end{cases}".to_owned())
+        );
+        assert_eq!(
+            to_mathml("\\begin{matrix} a", true),
+            Err("missing \\end{matrix}".to_owned())
+        );
+        assert_eq!(
+            to_mathml("a \\end{matrix}", true),
+            Err("\\end without \\begin".to_owned())
+        );
+    }
+}
@@ -1,6 +1,13 @@
+use crate::diagram;
+use crate::error::DoxyError;
+use crate::hash;
+use crate::highlight;
+use crate::math::{self, MathBackend};
+use crate::search;
 use roxmltree::{Document, Node};
-use serde::Serialize;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 // see here for structure:
 // https://raw.githubusercontent.com/doxygen/doxygen/master/templates/xml/compound.xsd
@@ -12,12 +19,21 @@ pub struct PageCommon {
     pub title: String,
     pub has_math: bool,
     pub subpage_refs: Vec<String>,
+    /// Problems that did not prevent rendering the page
+    #[serde(skip)]
+    pub warnings: Vec<DoxyError>,
+    /// Names of the XML files the compound was parsed from, with their content hashes
+    #[serde(skip)]
+    pub inputs: BTreeMap<String, String>,
 }
 
 #[derive(Serialize)]
 pub struct Page {
     pub common: PageCommon,
+    /// `brief` followed by `details`
     pub description: String,
+    pub brief: String,
+    pub details: String,
 }
 
 #[derive(Serialize)]
@@ -26,27 +42,120 @@ pub struct File {
     pub scopes: Vec<Scope>,
 }
 
+#[derive(Serialize)]
+pub struct ScopePage {
+    pub common: PageCommon,
+    pub scope: Scope,
+}
+
 #[derive(Serialize)]
 pub struct Scope {
     pub ref_id: String,
+    /// HTML for the heading, including template parameters and kind
     pub name: String,
+    /// Plain `a::b::C`, or the file name for file scopes
+    pub qualified_name: String,
     pub kind: String,
+    /// `brief` followed by `details`
+    pub description: String,
+    pub brief: String,
+    pub details: String,
+    /// Direct base classes
+    pub bases: Vec<ClassRef>,
+    /// Classes deriving directly from this one
+    pub derived: Vec<ClassRef>,
+    /// Inline SVG inheritance diagram, for classes with bases or derived classes
+    pub diagram: Option<String>,
     pub sections: Vec<Section>,
+    /// Members inherited from base classes, grouped by the class declaring them
+    pub inherited: Vec<InheritedGroup>,
+}
+
+#[derive(Serialize)]
+pub struct ClassRef {
+    /// None for classes doxygen has no documentation for, like standard library bases
+    pub ref_id: Option<String>,
+    /// HTML, linked if documented
+    pub name: String,
+    pub protection: String,
+    pub is_virtual: bool,
+}
+
+#[derive(Serialize)]
+pub struct InheritedGroup {
+    pub ref_id: String,
+    /// HTML, linked to the base class
+    pub name: String,
+    pub members: Vec<InheritedMember>,
+}
+
+#[derive(Serialize)]
+pub struct InheritedMember {
+    pub ref_id: String,
+    /// HTML, linked to the member in the base class
+    pub name: String,
+    pub protection: String,
 }
 
 #[derive(Serialize)]
 pub struct Section {
     pub name: Option<String>,
     pub description: Option<String>,
+    pub overload_sets: Vec<OverloadSet>,
+}
+
+impl Section {
+    pub fn members(&self) -> impl Iterator<Item = &Member> {
+        self.overload_sets.iter().flat_map(|set| &set.members)
+    }
+
+    pub fn members_mut(&mut self) -> impl Iterator<Item = &mut Member> {
+        self.overload_sets
+            .iter_mut()
+            .flat_map(|set| &mut set.members)
+    }
+}
+
+/// The members of a section sharing a name, in declaration order. Most sets have one member.
+#[derive(Serialize)]
+pub struct OverloadSet {
+    pub name: String,
+    /// Anchor for the whole set, the members keep their own
+    pub anchor: String,
     pub members: Vec<Member>,
 }
 
 #[derive(Serialize)]
 pub struct Member {
     pub ref_id: String,
+    pub name: String,
+    pub kind: String,
+    pub protection: String,
     pub definition: String,
+    /// `brief` followed by `details`
     pub description: String,
+    pub brief: String,
+    pub details: String,
     pub enum_values: Vec<EnumValue>,
+    pub qualifiers: Qualifiers,
+}
+
+/// Specifiers of functions and variables, from the memberdef attributes and the `argsstring`
+#[derive(Serialize, Default)]
+pub struct Qualifiers {
+    pub is_static: bool,
+    pub is_explicit: bool,
+    pub is_constexpr: bool,
+    pub is_virtual: bool,
+    pub is_pure_virtual: bool,
+    pub is_override: bool,
+    pub is_final: bool,
+    pub is_const: bool,
+    pub is_noexcept: bool,
+    /// `= delete`
+    pub is_deleted: bool,
+    /// `= default`
+    pub is_defaulted: bool,
 }
 
 #[derive(Serialize)]
@@ -54,175 +163,678 @@ pub struct EnumValue {
     pub ref_id: String,
     pub name: String,
     pub initializer: Option<String>,
+    /// `brief` followed by `details`
     pub description: String,
+    pub brief: String,
+    pub details: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Protection {
+    Public,
+    Protected,
+    Private,
+    Package,
+}
+
+impl Protection {
+    fn as_str(self) -> &'static str {
+        match self {
+            Protection::Public => "public",
+            Protection::Protected => "protected",
+            Protection::Private => "private",
+            Protection::Package => "package",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseOptions {
+    /// Render the classes and namespaces declared in a file as part of the file page. Otherwise
+    /// they are expected to get pages of their own.
+    pub inline_scopes: bool,
+    /// Members with any other protection level are left out.
+    pub protections: Vec<Protection>,
+    pub math: MathBackend,
+}
+
+impl ParseOptions {
+    fn includes(&self, protection: &str) -> bool {
+        self.protections.iter().any(|p| p.as_str() == protection)
+    }
 }
 
-struct Context {
+struct Context<'a> {
     has_math: bool,
+    options: &'a ParseOptions,
+    inputs: BTreeMap<String, String>,
+    warnings: Vec<DoxyError>,
 }
 
-pub fn parse_compound_page(xml_dir: &Path, ref_id: &str) -> Page {
-    let file_name = xml_dir.join(ref_id.to_owned() + ".xml");
-    let content = std::fs::read_to_string(&file_name).unwrap();
-    let doc = Document::parse(&content).unwrap();
-    let compounddef = doc
-        .root_element()
+impl Context<'_> {
+    /// Like errors, warnings are raised without a file name. Tags those raised since `first`.
+    fn tag_warnings(&mut self, first: usize, file_name: &Path) {
+        let tagged: Vec<_> = self
+            .warnings
+            .drain(first..)
+            .map(|w| w.in_file(file_name))
+            .collect();
+        self.warnings.extend(tagged);
+    }
+}
+
+fn xml_file_name(xml_dir: &Path, ref_id: &str) -> PathBuf {
+    xml_dir.join(ref_id.to_owned() + ".xml")
+}
+
+/// Reads `<ref_id>.xml` and hands its `<compounddef>` to `f`. Errors raised by `f` are tagged
+/// with the file name. Returns the result of `f` and the content hash of the file, so the build
+/// cache doesn't have to read it again.
+fn with_compounddef<T>(
+    xml_dir: &Path,
+    ref_id: &str,
+    f: impl FnOnce(Node) -> Result<T, DoxyError>,
+) -> Result<(T, String), DoxyError> {
+    let file_name = xml_file_name(xml_dir, ref_id);
+    let content = std::fs::read_to_string(&file_name).map_err(|e| DoxyError::io(&file_name, e))?;
+    let doc = Document::parse(&content).map_err(|source| DoxyError::Xml {
+        file: file_name.clone(),
+        source,
+    })?;
+    let root = doc.root_element();
+    let compounddef = root
         .children()
-        .find(|n| n.tag_name().name() == "compounddef")
-        .unwrap();
+        .find(|n| n.has_tag_name("compounddef"))
+        .ok_or_else(|| DoxyError::missing_element(root, "compounddef"))
+        .map_err(|e| e.in_file(&file_name))?;
+    let result = f(compounddef).map_err(|e| e.in_file(&file_name))?;
+    Ok((result, hash::content_hash(content.as_bytes())))
+}
 
-    let source = compounddef
-        .get_child("location")
-        .map(|l| l.attribute("file").unwrap())
-        .unwrap_or_default()
-        .to_owned();
+/// Key of a compound's XML file in `PageCommon::inputs`.
+fn input_name(ref_id: &str) -> String {
+    format!("{}.xml", ref_id)
+}
 
-    let title = compounddef.get_child_value("title").unwrap().to_owned();
+pub fn parse_compound_page(
+    xml_dir: &Path,
+    ref_id: &str,
+    options: &ParseOptions,
+) -> Result<Page, DoxyError> {
+    let (mut result, input_hash) = with_compounddef(xml_dir, ref_id, |compounddef| {
+        let source = match compounddef.get_child("location") {
+            Some(location) => location.attr("file")?.to_owned(),
+            None => String::new(),
+        };
 
-    let mut context = Context { has_math: false };
+        let title = compounddef.child_value("title")?.to_owned();
 
-    let description = parse_text(
-        compounddef.get_child("detaileddescription").unwrap(),
-        &mut context,
-    );
+        let mut context = Context {
+            has_math: false,
+            options,
+            inputs: BTreeMap::new(),
+            warnings: vec![],
+        };
 
-    let subpage_refs: Vec<_> = compounddef
-        .children()
-        .filter(|c| c.has_tag_name("innerpage"))
-        .map(|n| n.attribute("refid").unwrap().to_owned())
-        .collect();
+        let (brief, details) = parse_descriptions(compounddef, &mut context)?;
 
-    Page {
-        common: PageCommon {
-            ref_id: ref_id.to_owned(),
-            source,
-            title,
-            has_math: context.has_math,
-            subpage_refs,
-        },
-        description,
-    }
+        let subpage_refs = compounddef
+            .children()
+            .filter(|c| c.has_tag_name("innerpage"))
+            .map(|n| n.attr("refid").map(|r| r.to_owned()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        context.tag_warnings(0, &xml_file_name(xml_dir, ref_id));
+        Ok(Page {
+            common: PageCommon {
+                ref_id: ref_id.to_owned(),
+                source,
+                title,
+                has_math: context.has_math,
+                warnings: std::mem::take(&mut context.warnings),
+                subpage_refs,
+                inputs: context.inputs,
+            },
+            description: format!("{}{}", brief, details),
+            brief,
+            details,
+        })
+    })?;
+    result.common.inputs.insert(input_name(ref_id), input_hash);
+    Ok(result)
 }
 
-pub fn parse_compound_file(xml_dir: &Path, ref_id: &str) -> File {
-    let file_name = xml_dir.join(ref_id.to_owned() + ".xml");
-    let content = std::fs::read_to_string(file_name).unwrap();
-    let doc = Document::parse(&content).unwrap();
-    let compounddef = doc
-        .root_element()
-        .children()
-        .find(|n| n.tag_name().name() == "compounddef")
-        .unwrap();
+pub fn parse_compound_file(
+    xml_dir: &Path,
+    ref_id: &str,
+    options: &ParseOptions,
+) -> Result<File, DoxyError> {
+    let (mut result, input_hash) = with_compounddef(xml_dir, ref_id, |compounddef| {
+        let source = match compounddef.get_child("location") {
+            Some(location) => location.attr("file")?.to_owned(),
+            None => String::new(),
+        };
+
+        let title = compounddef.child_value("compoundname")?.to_owned();
 
-    let source = compounddef
-        .get_child("location")
-        .map(|l| l.attribute("file").unwrap())
-        .unwrap_or_default()
-        .to_owned();
-
-    let title = compounddef
-        .get_child_value("compoundname")
-        .unwrap()
-        .to_owned();
-
-    let mut scopes = vec![];
-
-    let mut context = Context { has_math: false };
-
-    for node in compounddef.children() {
-        match node.tag_name().name() {
-            "innerclass" | "innernamespace" => {
-                let inner_ref_id = node.attribute("refid").unwrap();
-                if let Some(scope) =
-                    parse_compound_scope(&title, xml_dir, inner_ref_id, &mut context)
-                {
-                    if !scope.sections.is_empty() {
-                        scopes.push(scope);
+        let mut scopes = vec![];
+
+        let mut context = Context {
+            has_math: false,
+            options,
+            inputs: BTreeMap::new(),
+            warnings: vec![],
+        };
+
+        for node in compounddef.children() {
+            match node.tag_name().name() {
+                "innerclass" | "innernamespace" if options.inline_scopes => {
+                    let inner_ref_id = node.attr("refid")?;
+                    if let Some(scope) =
+                        parse_compound_scope(Some(&title), xml_dir, inner_ref_id, &mut context)?
+                    {
+                        if !scope.sections.is_empty() {
+                            scopes.push(scope);
+                        }
                     }
                 }
+                // Per scope, classes and namespaces get pages of their own. The file's
+                // sectiondefs are parsed below; includes, the program listing and the like are
+                // not shown.
+                _ => {}
             }
-            _ => {} // TODO: fail here
         }
-    }
-    File {
-        common: PageCommon {
-            ref_id: ref_id.to_owned(),
-            source,
-            title,
-            has_math: context.has_math,
-            subpage_refs: vec![],
-        },
-        scopes,
-    }
+
+        // Free functions, variables, typedefs, macros and enums declared at file scope. Doxygen
+        // repeats namespace members here as well; those are owned by the namespace, which is
+        // visible from their id (`<compound id>_1<hash>`).
+        let member_prefix = format!("{}_", ref_id);
+        let sections = parse_sections(compounddef, &mut context, |memberdef| {
+            Ok(memberdef.attr("id")?.starts_with(&member_prefix))
+        })?;
+        if !sections.is_empty() {
+            let (brief, details) = parse_descriptions(compounddef, &mut context)?;
+            scopes.insert(
+                0,
+                Scope {
+                    ref_id: ref_id.to_owned(),
+                    name: format!(
+                        "<span class=\"kind_part\">file</span> {}",
+                        render_scope_name(&title)
+                    ),
+                    qualified_name: title.clone(),
+                    kind: "file".to_owned(),
+                    description: format!("{}{}", brief, details),
+                    brief,
+                    details,
+                    bases: vec![],
+                    derived: vec![],
+                    diagram: None,
+                    sections,
+                    inherited: vec![],
+                },
+            );
+        }
+
+        context.tag_warnings(0, &xml_file_name(xml_dir, ref_id));
+        Ok(File {
+            common: PageCommon {
+                ref_id: ref_id.to_owned(),
+                source,
+                title,
+                has_math: context.has_math,
+                warnings: std::mem::take(&mut context.warnings),
+                subpage_refs: vec![],
+                inputs: context.inputs,
+            },
+            scopes,
+        })
+    })?;
+    result.common.inputs.insert(input_name(ref_id), input_hash);
+    Ok(result)
+}
+
+pub fn parse_compound_scope_page(
+    xml_dir: &Path,
+    ref_id: &str,
+    options: &ParseOptions,
+) -> Result<ScopePage, DoxyError> {
+    let (mut result, input_hash) = with_compounddef(xml_dir, ref_id, |compounddef| {
+        let source = match compounddef.get_child("location") {
+            Some(location) => location.attr("file")?.to_owned(),
+            None => String::new(),
+        };
+
+        let title = compounddef.child_value("compoundname")?.to_owned();
+
+        let mut context = Context {
+            has_math: false,
+            options,
+            inputs: BTreeMap::new(),
+            warnings: vec![],
+        };
+
+        let scope = parse_scope(compounddef, None, &mut context)?;
+
+        context.tag_warnings(0, &xml_file_name(xml_dir, ref_id));
+        Ok(ScopePage {
+            common: PageCommon {
+                ref_id: ref_id.to_owned(),
+                source,
+                title,
+                has_math: context.has_math,
+                warnings: std::mem::take(&mut context.warnings),
+                subpage_refs: vec![],
+                inputs: context.inputs,
+            },
+            scope,
+        })
+    })?;
+    result.common.inputs.insert(input_name(ref_id), input_hash);
+    Ok(result)
 }
 
 fn parse_compound_scope(
-    parent_file_name: &str,
+    parent_file_name: Option<&str>,
     xml_dir: &Path,
     ref_id: &str,
     context: &mut Context,
-) -> Option<Scope> {
-    let file_name = xml_dir.join(ref_id.to_owned() + ".xml");
-    let content = std::fs::read_to_string(file_name);
-    if content.is_err() {
-        return None; // TODO: remove
-    }
-    let content = content.unwrap();
-    let doc = Document::parse(&content).unwrap();
-    let compounddef = doc
-        .root_element()
-        .children()
-        .find(|n| n.has_tag_name("compounddef"))
-        .unwrap();
+) -> Result<Option<Scope>, DoxyError> {
+    let first_warning = context.warnings.len();
+    let result = with_compounddef(xml_dir, ref_id, |compounddef| {
+        parse_scope(compounddef, parent_file_name, context)
+    });
+    context.tag_warnings(first_warning, &xml_file_name(xml_dir, ref_id));
+    match result {
+        Ok((scope, input_hash)) => {
+            context.inputs.insert(input_name(ref_id), input_hash);
+            Ok(Some(scope))
+        }
+        // doxygen does not write XML for every inner compound it lists
+        Err(DoxyError::Io { source, .. }) if source.kind() == std::io::ErrorKind::NotFound => {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Parses a class or namespace. If `parent_file_name` is given, only members declared in that
+/// file are included.
+fn parse_scope(
+    compounddef: Node,
+    parent_file_name: Option<&str>,
+    context: &mut Context,
+) -> Result<Scope, DoxyError> {
+    let ref_id = compounddef.attr("id")?.to_owned();
+    let kind = compounddef.attr("kind")?.to_owned();
 
-    let ref_id = compounddef.attribute("id").unwrap().to_owned();
-    let kind = compounddef.attribute("kind").unwrap().to_owned();
+    let qualified_name = compounddef.child_value("compoundname")?.to_owned();
 
     let name = format!(
         "{} <span class=\"kind_part\">{}</span> {}",
-        render_templateparamlist(compounddef, context),
+        render_templateparamlist(compounddef, context)?,
         kind,
-        render_scope_name(compounddef.get_child_value("compoundname").unwrap())
+        render_scope_name(&qualified_name)
     );
 
-    let sections = compounddef
-        .children()
-        .filter(|n| n.has_tag_name("sectiondef"))
-        .map(|sectiondef| {
-            let name = sectiondef.get_child_value("header").map(|v| v.to_owned());
-            let description = sectiondef
-                .get_child("description")
-                .map(|d| parse_text(d, context));
-            let members = sectiondef
-                .children()
-                .filter(|n| {
-                    n.has_tag_name("memberdef")
-                        && n.attribute("prot").unwrap() == "public"
-                        && n.attribute("kind").unwrap() != "friend"
-                        && n.get_child("location")
-                            .unwrap()
-                            .attribute("file")
-                            .unwrap()
-                            .ends_with(parent_file_name)
-                })
-                .map(|m| parse_member(m, context))
-                .collect();
-            Section {
-                name,
-                description,
-                members,
-            }
+    let sections = parse_sections(compounddef, context, |memberdef| {
+        Ok(match parent_file_name {
+            Some(parent_file_name) => memberdef
+                .child("location")?
+                .attr("file")?
+                .ends_with(parent_file_name),
+            None => true,
         })
-        .filter(|s| !s.members.is_empty())
-        .collect();
-    Some(Scope {
+    })?;
+    let (brief, details) = parse_descriptions(compounddef, context)?;
+    let bases = parse_class_refs(compounddef, "basecompoundref", context)?;
+    let derived = parse_class_refs(compounddef, "derivedcompoundref", context)?;
+    let diagram = if bases.is_empty() && derived.is_empty() {
+        None
+    } else {
+        let graph = match compounddef.get_child("inheritancegraph") {
+            Some(inheritancegraph) => {
+                parse_inheritancegraph(compounddef, inheritancegraph, &ref_id, context)?
+            }
+            None => {
+                let mut graph = diagram::Graph::default();
+                let current = graph.node(&qualified_name, Some(&ref_id));
+                graph.nodes[current].is_current = true;
+                for (class_refs, is_base) in [(&bases, true), (&derived, false)] {
+                    for class_ref in class_refs {
+                        let other = graph.node(
+                            &search::strip_html(&class_ref.name),
+                            class_ref.ref_id.as_deref(),
+                        );
+                        let (derived, base) = if is_base {
+                            (current, other)
+                        } else {
+                            (other, current)
+                        };
+                        graph.edges.push(diagram::Edge {
+                            derived,
+                            base,
+                            protection: class_ref.protection.clone(),
+                            is_virtual: class_ref.is_virtual,
+                        });
+                    }
+                }
+                graph
+            }
+        };
+        Some(diagram::render_svg(&graph, &ref_id))
+    };
+    let inherited = parse_inherited_members(compounddef, context)?;
+
+    Ok(Scope {
         ref_id,
         name,
+        qualified_name,
         kind,
+        description: format!("{}{}", brief, details),
+        brief,
+        details,
+        bases,
+        derived,
+        diagram,
         sections,
+        inherited,
     })
 }
 
+/// Groups the members in `listofallmembers` that are declared in a base class by that class.
+/// Left out are the functions the class reimplements, which doxygen links with `reimplements`
+/// as it matches signatures, and members hidden by a variable, type or enum of the same name.
+/// Base overloads with other signatures stay, since `listofallmembers` has no signatures to
+/// tell whether a `using` declaration brings them in.
+fn parse_inherited_members(
+    compounddef: Node,
+    context: &Context,
+) -> Result<Vec<InheritedGroup>, DoxyError> {
+    let listofallmembers = match compounddef.get_child("listofallmembers") {
+        Some(listofallmembers) => listofallmembers,
+        None => return Ok(vec![]),
+    };
+    let own_members: Vec<Node> = compounddef
+        .children()
+        .filter(|n| n.has_tag_name("sectiondef"))
+        .flat_map(|sectiondef| sectiondef.children())
+        .filter(|n| n.has_tag_name("memberdef"))
+        .collect();
+    let reimplemented = own_members
+        .iter()
+        .flat_map(|memberdef| memberdef.children())
+        .filter(|n| n.has_tag_name("reimplements"))
+        .map(|reimplements| reimplements.attr("refid"))
+        .collect::<Result<std::collections::HashSet<_>, _>>()?;
+    let mut hiding_names = std::collections::HashSet::new();
+    for memberdef in &own_members {
+        if memberdef.attr("kind")? != "function" {
+            hiding_names.insert(memberdef.child_value("name")?);
+        }
+    }
+
+    // listofallmembers only has ref ids for the declaring classes, their names come from the
+    // inheritance graph, which covers indirect bases too, or the list of direct bases
+    let mut class_names = std::collections::HashMap::new();
+    if let Some(inheritancegraph) = compounddef.get_child("inheritancegraph") {
+        for node in inheritancegraph
+            .children()
+            .filter(|n| n.has_tag_name("node"))
+        {
+            if let Some(link) = node.get_child("link") {
+                class_names.insert(link.attr("refid")?, node.child_value("label")?);
+            }
+        }
+    }
+    for basecompoundref in compounddef
+        .children()
+        .filter(|n| n.has_tag_name("basecompoundref"))
+    {
+        if let Some(ref_id) = basecompoundref.attribute("refid") {
+            class_names.insert(ref_id, basecompoundref.text().unwrap_or_default());
+        }
+    }
+
+    let own_ref_id = compounddef.attr("id")?;
+    let mut groups: Vec<InheritedGroup> = vec![];
+    for member in listofallmembers
+        .children()
+        .filter(|n| n.has_tag_name("member"))
+    {
+        let ref_id = member.attr("refid")?;
+        let protection = member.attr("prot")?;
+        let name = member.child_value("name")?;
+        // member ids are the id of the declaring compound followed by `_1` and an anchor
+        let class_ref_id = match ref_id.rsplit_once("_1") {
+            Some((class_ref_id, _)) => class_ref_id,
+            None => continue,
+        };
+        if class_ref_id == own_ref_id
+            || reimplemented.contains(ref_id)
+            || hiding_names.contains(name)
+            || !context.options.includes(protection)
+        {
+            continue;
+        }
+        let index = match groups.iter().position(|g| g.ref_id == class_ref_id) {
+            Some(index) => index,
+            None => {
+                let class_name = class_names
+                    .get(class_ref_id)
+                    .copied()
+                    .unwrap_or(class_ref_id);
+                groups.push(InheritedGroup {
+                    ref_id: class_ref_id.to_owned(),
+                    name: format!(
+                        "<a href=\"refid://{}\">{}</a>",
+                        class_ref_id,
+                        tera::escape_html(class_name)
+                    ),
+                    members: vec![],
+                });
+                groups.len() - 1
+            }
+        };
+        groups[index].members.push(InheritedMember {
+            ref_id: ref_id.to_owned(),
+            name: format!(
+                "<a href=\"refid://{}\">{}</a>",
+                ref_id,
+                tera::escape_html(name)
+            ),
+            protection: protection.to_owned(),
+        });
+    }
+    Ok(groups)
+}
+
+/// Parses `basecompoundref`s or `derivedcompoundref`s, keeping those with a selected protection.
+fn parse_class_refs(
+    compounddef: Node,
+    tag: &str,
+    context: &Context,
+) -> Result<Vec<ClassRef>, DoxyError> {
+    let mut class_refs = vec![];
+    for node in compounddef.children().filter(|n| n.has_tag_name(tag)) {
+        let protection = node.attr("prot")?;
+        if !context.options.includes(protection) {
+            continue;
+        }
+        let text = tera::escape_html(node.text().unwrap_or_default());
+        let ref_id = node.attribute("refid").map(|r| r.to_owned());
+        let name = match &ref_id {
+            Some(ref_id) => format!("<a href=\"refid://{}\">{}</a>", ref_id, text),
+            None => text,
+        };
+        class_refs.push(ClassRef {
+            ref_id,
+            name,
+            protection: protection.to_owned(),
+            is_virtual: node.attr("virt")? != "non-virtual",
+        });
+    }
+    Ok(class_refs)
+}
+
+/// Doxygen's `inheritancegraph` covers all ancestors, not just the direct bases. A node's
+/// `childnode`s are its bases. Edges to a class's direct bases and derived classes take their
+/// protection and virtuality from the `basecompoundref`s and `derivedcompoundref`s, the graph only
+/// has the protection. Edges with a protection that is left out are dropped, and so are the
+/// classes only reachable through them.
+fn parse_inheritancegraph(
+    compounddef: Node,
+    inheritancegraph: Node,
+    current_ref_id: &str,
+    context: &Context,
+) -> Result<diagram::Graph, DoxyError> {
+    let mut graph = diagram::Graph::default();
+    let mut node_ids = std::collections::HashMap::new();
+    let nodes: Vec<_> = inheritancegraph
+        .children()
+        .filter(|n| n.has_tag_name("node"))
+        .collect();
+    let mut current = None;
+    for node in &nodes {
+        let ref_id = node
+            .get_child("link")
+            .map(|link| link.attr("refid"))
+            .transpose()?;
+        let index = graph.node(node.child_value("label")?, ref_id);
+        if ref_id == Some(current_ref_id) {
+            graph.nodes[index].is_current = true;
+            current = Some(index);
+        }
+        node_ids.insert(node.attr("id")?, index);
+    }
+
+    // the compoundref naming the class of graph node `index`
+    let find_compoundref = |tag: &str, index: usize| {
+        let node: &diagram::Node = &graph.nodes[index];
+        compounddef
+            .children()
+            .filter(|n| n.has_tag_name(tag))
+            .find(|n| match (&node.ref_id, n.attribute("refid")) {
+                (Some(a), Some(b)) => a == b,
+                _ => n.text() == Some(node.label.as_str()),
+            })
+    };
+    let mut edges = vec![];
+    for node in &nodes {
+        let derived = node_ids[node.attr("id")?];
+        for childnode in node.children().filter(|n| n.has_tag_name("childnode")) {
+            let base = match node_ids.get(childnode.attr("refid")?) {
+                Some(&base) => base,
+                None => continue,
+            };
+            let compoundref = if Some(derived) == current {
+                find_compoundref("basecompoundref", base)
+            } else if Some(base) == current {
+                find_compoundref("derivedcompoundref", derived)
+            } else {
+                None
+            };
+            let (protection, is_virtual) = match compoundref {
+                Some(compoundref) => (
+                    compoundref.attr("prot")?,
+                    compoundref.attr("virt")? != "non-virtual",
+                ),
+                // e.g. "public-inheritance"
+                None => {
+                    let relation = childnode.attr("relation")?;
+                    (relation.split('-').next().unwrap_or_default(), false)
+                }
+            };
+            if context.options.includes(protection) {
+                edges.push(diagram::Edge {
+                    derived,
+                    base,
+                    protection: protection.to_owned(),
+                    is_virtual,
+                });
+            }
+        }
+    }
+    graph.edges = edges;
+    Ok(match current {
+        Some(current) => graph.connected_to(current),
+        None => graph,
+    })
+}
+
+/// Parses the `sectiondef`s of a compound, keeping the members with a selected protection level
+/// for which `include` returns true. Sections without members are dropped.
+fn parse_sections(
+    compounddef: Node,
+    context: &mut Context,
+    include: impl Fn(Node) -> Result<bool, DoxyError>,
+) -> Result<Vec<Section>, DoxyError> {
+    let mut sections = vec![];
+    for sectiondef in compounddef
+        .children()
+        .filter(|n| n.has_tag_name("sectiondef"))
+    {
+        let name = sectiondef.get_child_value("header").map(|v| v.to_owned());
+        let description = sectiondef
+            .get_child("description")
+            .map(|d| parse_text(d, context))
+            .transpose()?;
+        let mut members = vec![];
+        for memberdef in sectiondef
+            .children()
+            .filter(|n| n.has_tag_name("memberdef"))
+        {
+            if !context.options.includes(memberdef.attr("prot")?)
+                || memberdef.attr("kind")? == "friend"
+                || !include(memberdef)?
+            {
+                continue;
+            }
+            if let Some(member) = parse_member(memberdef, context)? {
+                members.push(member);
+            }
+        }
+        if !members.is_empty() {
+            sections.push(Section {
+                name,
+                description,
+                overload_sets: group_overloads(compounddef.attr("id")?, members),
+            });
+        }
+    }
+    Ok(sections)
+}
+
+fn group_overloads(scope_ref_id: &str, members: Vec<Member>) -> Vec<OverloadSet> {
+    let mut sets: Vec<OverloadSet> = vec![];
+    for member in members {
+        match sets.iter_mut().find(|set| set.name == member.name) {
+            Some(set) => set.members.push(member),
+            None => sets.push(OverloadSet {
+                name: member.name.clone(),
+                anchor: overload_anchor(scope_ref_id, &member.name),
+                members: vec![member],
+            }),
+        }
+    }
+    sets
+}
+
+/// `<scope ref id>-<name>`, with characters that need escaping in URLs, like those of operators,
+/// replaced by their code. Doxygen ids have no `-`, so these never clash with member anchors.
+fn overload_anchor(scope_ref_id: &str, name: &str) -> String {
+    let mut anchor = format!("{}-", scope_ref_id);
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            anchor.push(c);
+        } else {
+            anchor.push_str(&format!(".{:x}", c as u32));
+        }
+    }
+    anchor
+}
+
 fn render_scope_name(name: &str) -> String {
     let mut name = tera::escape_html(name).replace("::", "::&#8203;");
     if let Some(pos) = name.rfind("::&#8203;") {
@@ -235,34 +847,60 @@ fn render_scope_name(name: &str) -> String {
     name
 }
 
-fn parse_member(memberdef: Node, context: &mut Context) -> Member {
-    let ref_id = memberdef.attribute("id").unwrap().to_owned();
-    let return_type = parse_text(memberdef.get_child("type").unwrap(), context);
-    let name = memberdef
-        .get_child_value("name")
-        .map(tera::escape_html)
-        .unwrap();
-    let template = render_templateparamlist(memberdef, context);
-    let args = render_function_args(memberdef, context);
+/// Returns `None`, with a warning, for members of kinds that can't be rendered, like Qt signals
+/// and slots, so they don't take down the whole page.
+fn parse_member(memberdef: Node, context: &mut Context) -> Result<Option<Member>, DoxyError> {
+    let ref_id = memberdef.attr("id")?.to_owned();
+    let protection = memberdef.attr("prot")?.to_owned();
+    let kind = memberdef.attr("kind")?;
+    // macros have neither a type nor typed parameters
+    let is_define = kind == "define";
+    let return_type = match memberdef.get_child("type") {
+        Some(t) => parse_text(t, context)?,
+        None if is_define => String::new(),
+        None => return Err(DoxyError::missing_element(memberdef, "type")),
+    };
+    let qualifiers = parse_qualifiers(memberdef)?;
+    // some doxygen versions repeat the specifiers in the type
+    let mut return_type = return_type.as_str();
+    while let Some(rest) = ["static ", "virtual ", "explicit ", "constexpr "]
+        .iter()
+        .find_map(|specifier| return_type.strip_prefix(specifier))
+    {
+        return_type = rest;
+    }
+    let (prefix, suffix, assignment) = render_qualifiers(&qualifiers);
+    let plain_name = memberdef.child_value("name")?;
+    let name = tera::escape_html(plain_name);
+    let template = render_templateparamlist(memberdef, context)?;
+    let args = if is_define {
+        render_macro_params(memberdef)
+    } else {
+        render_function_args(memberdef, context)?
+    };
 
-    let definition = match memberdef.attribute("kind").unwrap() {
+    let definition = match kind {
         "function" | "event" if !return_type.is_empty() => format!(
-            "{}<span class=\"member_name\">{}</span>{} → <span class=\"rettype\">{}</span>",
-            template, name, args, return_type
+            "{}{}<span class=\"member_name\">{}</span>{}{} → <span class=\"rettype\">{}</span>{}",
+            template, prefix, name, args, suffix, return_type, assignment
+        ),
+        "function" | "event" => format!(
+            "{}{}<span class=\"member_name\">{}</span>{}{}{}",
+            template, prefix, name, args, suffix, assignment
         ),
-        "function" | "event" => format!("{}<span class=\"member_name\">{}</span>{}", template, name, args),
         "typedef" => format!(
             "{}<span class=\"keyword\">using</span> <span class=\"member_name\">{}</span> = <span class=\"type\">{}</span>",
             template, name, return_type
         ),
         "variable" | "property" => {
             let defval = if let Some(initializer) = memberdef.get_child("initializer"){
-                format!(" <span class=\"defval\">{}</span>", parse_text(initializer, context))
+                format!(" <span class=\"defval\">{}</span>", parse_text(initializer, context)?)
             } else {
                 "".to_owned()
             };
             format!(
-                "<span class=\"type\">{}</span> <span class=\"member_name\">{}</span>{}",
+                "{}<span class=\"type\">{}</span> <span class=\"member_name\">{}</span>{}",
+                prefix,
                 return_type,
                 name,
                 defval)
@@ -270,55 +908,181 @@ fn parse_member(memberdef: Node, context: &mut Context) -> Member {
         "enum" => {
             format!("<span class=\"keyword\">enum</span> <span class=\"member_name\">{}</span>", name)
         },
-        //_ => format!("{}", memberdef.get_child_value("definition").unwrap()),
-        _ => panic!(
-            "not implemented: {} ({})",
-            memberdef.attribute("kind").unwrap(),
-            ref_id
-        ),
+        "define" => {
+            let replacement = if let Some(initializer) = memberdef.get_child("initializer") {
+                format!(" <span class=\"defval\">{}</span>", parse_text(initializer, context)?)
+            } else {
+                "".to_owned()
+            };
+            format!(
+                "<span class=\"keyword\">#define</span> <span class=\"member_name\">{}</span>{}{}",
+                name, args, replacement
+            )
+        },
+        _ => {
+            context
+                .warnings
+                .push(DoxyError::unsupported_kind(memberdef, kind));
+            return Ok(None);
+        }
     };
 
-    let enum_values = if memberdef.attribute("kind").unwrap() == "enum" {
+    let enum_values = if kind == "enum" {
         memberdef
             .children()
             .filter(|c| c.has_tag_name("enumvalue"))
             .map(|value| {
-                let description = {
-                    let brief = parse_text(value.get_child("briefdescription").unwrap(), context);
-                    let detailed =
-                        parse_text(value.get_child("detaileddescription").unwrap(), context);
-                    brief + &detailed
-                };
-                EnumValue {
-                    ref_id: value.attribute("id").unwrap().to_owned(),
-                    name: value.get_child_value("name").unwrap().to_owned(),
+                let (brief, details) = parse_descriptions(value, context)?;
+                Ok(EnumValue {
+                    ref_id: value.attr("id")?.to_owned(),
+                    name: value.child_value("name")?.to_owned(),
                     initializer: value
                         .get_child("initializer")
-                        .map(|i| parse_text(i, context)),
-                    description,
-                }
+                        .map(|i| parse_text(i, context))
+                        .transpose()?,
+                    description: format!("{}{}", brief, details),
+                    brief,
+                    details,
+                })
             })
-            .collect()
+            .collect::<Result<_, DoxyError>>()?
     } else {
         vec![]
     };
 
-    let description = {
-        let brief = parse_text(memberdef.get_child("briefdescription").unwrap(), context);
-        let detailed = parse_text(memberdef.get_child("detaileddescription").unwrap(), context);
-        brief + &detailed
-    }
-    .trim()
-    .to_owned();
-    Member {
+    let (brief, details) = parse_descriptions(memberdef, context)?;
+    let description = format!("{}{}", brief, details);
+    Ok(Some(Member {
         ref_id,
+        name: plain_name.to_owned(),
+        kind: kind.to_owned(),
+        protection,
         definition,
         description,
+        brief,
+        details,
         enum_values,
+        qualifiers,
+    }))
+}
+
+/// The `briefdescription` and `detaileddescription` of a node, trimmed. Either may be missing.
+fn parse_descriptions(node: Node, context: &mut Context) -> Result<(String, String), DoxyError> {
+    let mut parse = |tag| -> Result<String, DoxyError> {
+        Ok(match node.get_child(tag) {
+            Some(description) => parse_text(description, context)?.trim().to_owned(),
+            None => String::new(),
+        })
+    };
+    Ok((parse("briefdescription")?, parse("detaileddescription")?))
+}
+
+fn parse_qualifiers(memberdef: Node) -> Result<Qualifiers, DoxyError> {
+    let is_yes = |attribute| memberdef.attribute(attribute) == Some("yes");
+    let virt = memberdef.attribute("virt").unwrap_or("non-virtual");
+    let mut qualifiers = Qualifiers {
+        is_static: is_yes("static"),
+        is_explicit: is_yes("explicit"),
+        is_constexpr: is_yes("constexpr"),
+        is_virtual: virt != "non-virtual",
+        is_pure_virtual: virt == "pure-virtual",
+        is_final: is_yes("final"),
+        is_const: is_yes("const"),
+        is_noexcept: is_yes("noexcept"),
+        ..Qualifiers::default()
+    };
+    if memberdef.attr("kind")? != "function" {
+        return Ok(qualifiers);
+    }
+
+    // the argsstring is the only place for override, = delete and = default, and older doxygen
+    // versions have no attributes for const and noexcept either
+    let argsstring = memberdef.get_child_value("argsstring").unwrap_or_default();
+    let mut depth = 0;
+    let mut end_of_args = 0;
+    for (i, c) in argsstring.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    end_of_args = i + 1;
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let trailing = argsstring[end_of_args..].replace('=', " = ");
+    let mut tokens = trailing.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            "const" => qualifiers.is_const = true,
+            "override" => qualifiers.is_override = true,
+            "final" => qualifiers.is_final = true,
+            "=" => match tokens.peek() {
+                Some(&"0") => qualifiers.is_pure_virtual = true,
+                Some(&"delete") => qualifiers.is_deleted = true,
+                Some(&"default") => qualifiers.is_defaulted = true,
+                _ => {}
+            },
+            _ if token.starts_with("noexcept") => {
+                qualifiers.is_noexcept |= !token.starts_with("noexcept(false)")
+            }
+            _ => {}
+        }
     }
+    if qualifiers.is_pure_virtual || qualifiers.is_override || qualifiers.is_final {
+        qualifiers.is_virtual = true;
+    }
+    Ok(qualifiers)
+}
+
+/// HTML for the specifiers before the name, after the arguments and the `= ...` at the end.
+fn render_qualifiers(qualifiers: &Qualifiers) -> (String, String, String) {
+    let keyword = |k| format!("<span class=\"keyword\">{}</span>", k);
+    let mut prefix = String::new();
+    let mut add_prefix = |is_set: bool, k| {
+        if is_set {
+            prefix.push_str(&keyword(k));
+            prefix.push(' ');
+        }
+    };
+    add_prefix(qualifiers.is_static, "static");
+    add_prefix(qualifiers.is_explicit, "explicit");
+    add_prefix(qualifiers.is_constexpr, "constexpr");
+    // overriding functions are virtual as well, but only say so where it was introduced
+    add_prefix(
+        qualifiers.is_virtual && !qualifiers.is_override && !qualifiers.is_final,
+        "virtual",
+    );
+
+    let mut suffix = String::new();
+    for (is_set, k) in [
+        (qualifiers.is_const, "const"),
+        (qualifiers.is_noexcept, "noexcept"),
+        (qualifiers.is_override, "override"),
+        (qualifiers.is_final, "final"),
+    ] {
+        if is_set {
+            suffix.push(' ');
+            suffix.push_str(&keyword(k));
+        }
+    }
+
+    let assignment = if qualifiers.is_pure_virtual {
+        " = <span class=\"keyword\">0</span>".to_owned()
+    } else if qualifiers.is_deleted {
+        format!(" = {}", keyword("delete"))
+    } else if qualifiers.is_defaulted {
+        format!(" = {}", keyword("default"))
+    } else {
+        String::new()
+    };
+    (prefix, suffix, assignment)
 }
 
-fn render_templateparamlist(memberdef: Node, context: &mut Context) -> String {
+fn render_templateparamlist(memberdef: Node, context: &mut Context) -> Result<String, DoxyError> {
     if let Some(templateparamlist) = memberdef.get_child("templateparamlist") {
         let mut s = String::new();
         for param in templateparamlist
@@ -328,46 +1092,69 @@ fn render_templateparamlist(memberdef: Node, context: &mut Context) -> String {
             if !s.is_empty() {
                 s.push_str(", ");
             }
-            s.push_str(&parse_text(param.get_child("type").unwrap(), context));
+            s.push_str(&parse_text(param.child("type")?, context)?);
             if let Some(defval) = param.get_child_value("defval") {
                 s.push_str(defval);
             }
         }
-        format!(
+        Ok(format!(
             "<span class=\"templateparamlist\">template &lt;{}&gt;</span>",
             s
-        )
+        ))
     } else {
-        "".to_owned()
+        Ok("".to_owned())
+    }
+}
+
+/// Renders the parameter list of a function-like macro, e.g. `(a, b)`. Object-like macros have no
+/// `<param>` at all and get an empty string.
+fn render_macro_params(memberdef: Node) -> String {
+    let params: Vec<_> = memberdef
+        .children()
+        .filter(|n| n.has_tag_name("param"))
+        .collect();
+    if params.is_empty() {
+        return "".to_owned();
     }
+    let names = params
+        .iter()
+        .filter_map(|param| param.get_child_value("defname"))
+        .map(|defname| {
+            format!(
+                "<span class=\"declname\">{}</span>",
+                tera::escape_html(defname)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("({})", names.join(", "))
 }
 
-fn render_function_args(memberdef: Node, context: &mut Context) -> String {
-    let args: Vec<_> = memberdef
+fn render_function_args(memberdef: Node, context: &mut Context) -> Result<String, DoxyError> {
+    let args = memberdef
         .children()
         .filter(|n| n.has_tag_name("param"))
         .map(|param| {
             let mut result = format!(
                 "<span class=\"type\">{}</span>",
-                parse_text(param.get_child("type").unwrap(), context)
+                parse_text(param.child("type")?, context)?
                     .replace(" &amp;", "&amp;")
                     .replace(" *", "*")
             );
             if let Some(declname) = param.get_child("declname") {
                 result.push_str(&format!(
                     " <span class=\"declname\">{}</span>",
-                    parse_text(declname, context)
+                    parse_text(declname, context)?
                 ));
             }
             if let Some(defval) = param.get_child("defval") {
                 result.push_str(&format!(
                     " = <span class=\"defval\">{}</span>",
-                    parse_text(defval, context)
+                    parse_text(defval, context)?
                 ));
             }
-            result
+            Ok(result)
         })
-        .collect();
+        .collect::<Result<Vec<_>, DoxyError>>()?;
 
     let is_multiline = !args.is_empty(); // args.iter().map(|a| a.len() + 2).sum::<usize>() >= 60;
     let newline = "<br/>&nbsp;&nbsp;&nbsp;&nbsp;";
@@ -390,23 +1177,26 @@ fn render_function_args(memberdef: Node, context: &mut Context) -> String {
         s.push_str(arg);
     }
     s.push(')');
-    s
+    Ok(s)
 }
 
-fn parse_text(node: Node, context: &mut Context) -> String {
+fn parse_text(node: Node, context: &mut Context) -> Result<String, DoxyError> {
     let mut s = String::new();
     let mut skip_next_chars = 0usize;
     for c in node.children() {
         match c.tag_name().name() {
             "" => {
-                s.push_str(&tera::escape_html(&c.text().unwrap()[skip_next_chars..]));
+                let text = c.text().unwrap_or_default();
+                s.push_str(&tera::escape_html(
+                    text.get(skip_next_chars..).unwrap_or_default(),
+                ));
                 skip_next_chars = 0usize;
             }
             "para" => {
-                s.push_str(&format!("<p>{}</p>", parse_text(c, context)));
+                s.push_str(&format!("<p>{}</p>", parse_text(c, context)?));
             }
             "simplesect" => {
-                let kind = c.attribute("kind").unwrap();
+                let kind = c.attr("kind")?;
                 let css_class = match kind {
                     "warning" | "attention" => Some("alert-warning"),
                     "info" | "note" | "remark" => Some("alert-info"),
@@ -416,7 +1206,7 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                     s.push_str(&format!(
                         "<div class=\"alert {}\">{}</div>",
                         css_class,
-                        parse_text(c.get_child("para").unwrap(), context)
+                        parse_text(c.child("para")?, context)?
                     ));
                 } else {
                     let kind_name = match kind {
@@ -426,33 +1216,33 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                     s.push_str(&format!(
                         "<dl><dt>{}</dt><dd>{}</dd></dl>",
                         kind_name,
-                        parse_text(c.get_child("para").unwrap(), context)
+                        parse_text(c.child("para")?, context)?
                     ));
                 }
             }
             "blockquote" => {
                 s.push_str("<blockquote>");
-                s.push_str(&parse_text(c.get_child("para").unwrap(), context));
+                s.push_str(&parse_text(c.child("para")?, context)?);
                 s.push_str("</blockquote>");
             }
             "ref" => {
                 // TODO: add link
                 s.push_str(&format!(
                     "<a href=\"refid://{}\">{}</a>",
-                    c.attribute("refid").unwrap(),
-                    c.text().unwrap()
+                    c.attr("refid")?,
+                    c.text().unwrap_or_default()
                 ));
             }
             "ulink" => {
                 s.push_str(&format!(
                     "<a href=\"{}\">{}</a>",
-                    c.attribute("url").unwrap(),
-                    parse_text(c, context)
+                    c.attr("url")?,
+                    parse_text(c, context)?
                 ));
             }
             "xrefsect" => {
-                let xreftitle = c.get_child_value("xreftitle").unwrap();
-                let xrefdescription = parse_text(c.get_child("xrefdescription").unwrap(), context);
+                let xreftitle = c.child_value("xreftitle")?;
+                let xrefdescription = parse_text(c.child("xrefdescription")?, context)?;
                 let css_class = "alert-danger";
                 s.push_str(&format!(
                     "<div class=\"alert {}\"><h5>{}</h5>{}</div>",
@@ -460,10 +1250,10 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                 ));
             }
             "parameterlist" => {
-                let use_table = match c.attribute("kind").unwrap() {
+                let use_table = match c.attr("kind")? {
                     "param" | "templateparam" => true,
                     "exception" => false,
-                    kind => panic!("parameterlist kind not implemented: {}", kind),
+                    kind => return Err(DoxyError::unsupported_kind(c, kind)),
                 };
                 if use_table {
                     s.push_str("<table class=\"parameterlist\">");
@@ -471,7 +1261,7 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                     s.push_str("<dl class=\"parameterlist\">");
                 }
                 for item in c.children().filter(|n| n.has_tag_name("parameteritem")) {
-                    let parameternamelist = item.get_child("parameternamelist").unwrap();
+                    let parameternamelist = item.child("parameternamelist")?;
                     let names = parameternamelist
                         .children()
                         .filter(|c| c.has_tag_name("parametername"))
@@ -479,8 +1269,7 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                         .collect::<Vec<_>>()
                         .join(", ");
                     if !names.is_empty() {
-                        let description =
-                            parse_text(item.get_child("parameterdescription").unwrap(), context);
+                        let description = parse_text(item.child("parameterdescription")?, context)?;
                         let name = tera::escape_html(&names);
                         if use_table {
                             s.push_str(&format!(
@@ -502,14 +1291,10 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                 }
             }
             tag @ "itemizedlist" | tag @ "orderedlist" => {
-                let tag = match tag {
-                    "itemizedlist" => "ul",
-                    "orderedlist" => "ol",
-                    _ => unimplemented!(),
-                };
+                let tag = if tag == "itemizedlist" { "ul" } else { "ol" };
                 s.push_str(&format!("<{}>", tag));
                 for item in c.children().filter(|n| n.has_tag_name("listitem")) {
-                    s.push_str(&format!("<li>{}</li>", parse_text(item, context)));
+                    s.push_str(&format!("<li>{}</li>", parse_text(item, context)?));
                 }
                 s.push_str(&format!("</{}>", tag));
             }
@@ -518,13 +1303,13 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                 for row in c.children().filter(|n| n.has_tag_name("row")) {
                     s.push_str("<tr>");
                     for entry in row.children().filter(|n| n.has_tag_name("entry")) {
-                        let is_th = entry.attribute("thead").unwrap() == "yes";
+                        let is_th = entry.attr("thead")? == "yes";
                         if is_th {
                             s.push_str("<th>");
                         } else {
                             s.push_str("<td>");
                         }
-                        s.push_str(&parse_text(entry, context));
+                        s.push_str(&parse_text(entry, context)?);
                         if is_th {
                             s.push_str("</th>");
                         } else {
@@ -536,47 +1321,24 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                 s.push_str("</table>");
             }
             "programlisting" => {
-                s.push_str("<pre class=\"programlisting\">");
-                let mut dedent = usize::MAX;
-                for codeline in c.children().filter(|n| n.has_tag_name("codeline")) {
-                    // count leading spaces
-                    let mut n_indents = 0;
-                    let mut has_content = false;
-                    if let Some(highlight) =
-                        codeline.children().find(|n| n.has_tag_name("highlight"))
-                    {
-                        for token in highlight.children() {
-                            match token.tag_name().name() {
-                                "sp" => {
-                                    n_indents += 1;
-                                }
-                                _ => {
-                                    has_content = true;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    if has_content {
-                        dedent = std::cmp::min(dedent, n_indents);
-                    }
-                }
-                for codeline in c.children().filter(|n| n.has_tag_name("codeline")) {
-                    let codeline = format!("{}<br/>", parse_text(codeline, context));
-                    let codeline = codeline.replacen("&nbsp;", "", dedent);
-                    s.push_str(&codeline);
+                if let Some(language) = c.attribute("filename").and_then(highlight::language_for) {
+                    s.push_str(&format!(
+                        "<pre class=\"programlisting\">{}</pre>",
+                        highlight_listing(c, language)
+                    ));
+                } else {
+                    s.push_str(&render_doxygen_listing(c, context)?);
                 }
-                s.push_str("</pre>");
             }
             "highlight" => {
                 s.push_str(&format!(
                     "<span class=\"highlight-{}\">{}</span>",
-                    c.attribute("class").unwrap(),
-                    parse_text(c, context)
+                    c.attr("class")?,
+                    parse_text(c, context)?
                 ));
             }
             "image" => {
-                let path = c.attribute("name").unwrap();
+                let path = c.attr("name")?;
 
                 // parse style information like:
                 // <image ...></image>{width: 80%}
@@ -594,15 +1356,40 @@ fn parse_text(node: Node, context: &mut Context) -> String {
             }
             "formula" => {
                 context.has_math = true;
-                // bring formula into format that MathJAX understands
-                let formula = c.text().unwrap();
-                let formula = formula.trim_matches('$');
-                s.push_str(&format!("\\({}\\)", formula));
+                let formula = c.text().unwrap_or_default();
+                let (tex, display) = math::split_formula(formula);
+                let html = match context.options.math {
+                    MathBackend::Mathml => match math::to_mathml(tex, display) {
+                        Ok(mathml) => mathml,
+                        Err(message) => {
+                            context
+                                .warnings
+                                .push(DoxyError::invalid_formula(c, formula, message));
+                            format!(
+                                "<code class=\"math-error\">{}</code>",
+                                tera::escape_html(tex)
+                            )
+                        }
+                    },
+                    // in the format that MathJax and KaTeX understand
+                    MathBackend::Tex if tex.starts_with("\\begin") => tera::escape_html(tex),
+                    MathBackend::Tex if display => format!("\\[{}\\]", tera::escape_html(tex)),
+                    MathBackend::Tex => format!("\\({}\\)", tera::escape_html(tex)),
+                };
+                if display {
+                    s.push_str(&format!("<span class=\"math-display\">{}</span>", html));
+                } else {
+                    s.push_str(&html);
+                }
             }
             "htmlonly" => {
                 let node_range = c.range();
                 let input_text = c.document().input_text();
-                s.push_str(&input_text[node_range.start + 10..node_range.end - 11]);
+                s.push_str(
+                    input_text
+                        .get(node_range.start + 10..node_range.end - 11)
+                        .unwrap_or_default(),
+                );
             }
             "variablelist" => {
                 s.push_str("<dl class=\"variablelist\">");
@@ -611,47 +1398,58 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                         "varlistentry" => {
                             s.push_str(&format!(
                                 "<dt>{}</dt>",
-                                parse_text(term.get_child("term").unwrap(), context)
+                                parse_text(term.child("term")?, context)?
                             ));
                         }
                         "listitem" => {
-                            s.push_str(&format!("<dd>{}</dd>", parse_text(term, context)));
+                            s.push_str(&format!("<dd>{}</dd>", parse_text(term, context)?));
                         }
                         "" => {}
                         tag => {
-                            panic!("unexpected tag: {}", tag);
+                            return Err(DoxyError::unsupported_kind(c, tag));
                         }
                     }
                 }
                 s.push_str("</dl>");
             }
             "anchor" => {
-                let id = c.attribute("id").unwrap();
+                let id = c.attr("id")?;
                 s.push_str(&format!("<a name=\"{}\"></a>", id));
             }
             // tag pass-through
             "bold" => {
-                s.push_str(&format!("<bold>{}</bold>", parse_text(c, context)));
+                s.push_str(&format!("<bold>{}</bold>", parse_text(c, context)?));
             }
             "emphasis" => {
-                s.push_str(&format!("<em>{}</em>", parse_text(c, context)));
+                s.push_str(&format!("<em>{}</em>", parse_text(c, context)?));
+            }
+            "verbatim" | "preformatted" if c.children().all(|n| n.is_text()) => {
+                let mut text = String::new();
+                collect_code_text(c, &mut text, &mut vec![]);
+                s.push_str(&format!(
+                    "<pre>{}</pre>",
+                    highlight::highlight(&text, &highlight::GENERIC)
+                ));
             }
             "verbatim" | "preformatted" => {
-                s.push_str(&format!("<pre>{}</pre>", parse_text(c, context)));
+                s.push_str(&format!("<pre>{}</pre>", parse_text(c, context)?));
             }
             "computeroutput" => {
-                s.push_str(&format!("<tt>{}</tt>", parse_text(c, context)));
+                s.push_str(&format!("<tt>{}</tt>", parse_text(c, context)?));
             }
             "superscript" => {
-                s.push_str(&format!("<sup>{}</sup>", parse_text(c, context)));
+                s.push_str(&format!("<sup>{}</sup>", parse_text(c, context)?));
             }
             "subscript" => {
-                s.push_str(&format!("<sub>{}</sub>", parse_text(c, context)));
+                s.push_str(&format!("<sub>{}</sub>", parse_text(c, context)?));
             }
             tag @ "sect1" | tag @ "sect2" | tag @ "sect3" | tag @ "sect4" | tag @ "sect5" => {
-                let title = c.children().find(|n| n.has_tag_name("title")).unwrap();
-                let level = tag.chars().nth(4).unwrap().to_digit(10).unwrap() + 1;
-                let id = c.attribute("id").unwrap();
+                let title = c
+                    .children()
+                    .find(|n| n.has_tag_name("title"))
+                    .ok_or_else(|| DoxyError::missing_element(c, "title"))?;
+                let level = tag.chars().nth(4).and_then(|l| l.to_digit(10)).unwrap_or(0) + 1;
+                let id = c.attr("id")?;
                 // TODO: generate proper anchors. Currently:
                 // sect.id = md_Developer_guide_Cutsim_Gouge_excess_1cutsim_ge_draw_mode_offset
                 // ulink.url = #cutsim_ge_draw_mode_offset
@@ -659,18 +1457,20 @@ fn parse_text(node: Node, context: &mut Context) -> String {
                 s.push_str(&format!(
                     "<h{}>{}</h{}>",
                     level,
-                    parse_text(title, context),
+                    parse_text(title, context)?,
                     level
                 ));
-                s.push_str(&parse_text(c, context));
+                s.push_str(&parse_text(c, context)?);
             }
             "title" => {} // handled by sectN
             "heading" => {
-                let level = c.attribute("level").unwrap().parse::<usize>().unwrap();
+                let level = c.attr("level")?.parse::<usize>().map_err(|_| {
+                    DoxyError::unsupported_kind(c, c.attribute("level").unwrap_or_default())
+                })?;
                 s.push_str(&format!(
                     "<h{}>{}</h{}>",
                     level,
-                    parse_text(c, context),
+                    parse_text(c, context)?,
                     level
                 ));
             }
@@ -692,7 +1492,103 @@ fn parse_text(node: Node, context: &mut Context) -> String {
             }
         }
     }
-    s
+    Ok(s)
+}
+
+/// A `programlisting` with doxygen's own highlighting, for listings without a file name, like
+/// `\code` blocks, and for languages highlight.rs doesn't know. Lines are dedented by their
+/// common indentation.
+fn render_doxygen_listing(listing: Node, context: &mut Context) -> Result<String, DoxyError> {
+    let mut html = String::new();
+    html.push_str("<pre class=\"programlisting\">");
+    let mut dedent = usize::MAX;
+    for codeline in listing.children().filter(|n| n.has_tag_name("codeline")) {
+        // count leading spaces
+        let mut n_indents = 0;
+        let mut has_content = false;
+        if let Some(highlight) = codeline.children().find(|n| n.has_tag_name("highlight")) {
+            for token in highlight.children() {
+                match token.tag_name().name() {
+                    "sp" => {
+                        n_indents += 1;
+                    }
+                    _ => {
+                        has_content = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if has_content {
+            dedent = std::cmp::min(dedent, n_indents);
+        }
+    }
+    for codeline in listing.children().filter(|n| n.has_tag_name("codeline")) {
+        let codeline = format!("{}<br/>", parse_text(codeline, context)?);
+        let codeline = codeline.replacen("&nbsp;", "", dedent);
+        html.push_str(&codeline);
+    }
+    html.push_str("</pre>");
+    Ok(html)
+}
+
+/// Highlights a `programlisting` from its text, keeping the links doxygen added. Lines are
+/// dedented by their common indentation.
+fn highlight_listing(listing: Node, language: &highlight::Language) -> String {
+    let mut lines = vec![];
+    for codeline in listing.children().filter(|n| n.has_tag_name("codeline")) {
+        let mut text = String::new();
+        let mut links = vec![];
+        collect_code_text(codeline, &mut text, &mut links);
+        lines.push((text, links));
+    }
+    let indent = |text: &str| text.len() - text.trim_start_matches(' ').len();
+    let dedent = lines
+        .iter()
+        .filter(|(text, _)| !text.trim().is_empty())
+        .map(|(text, _)| indent(text))
+        .min()
+        .unwrap_or(0);
+
+    let mut code = String::new();
+    let mut all_links = vec![];
+    for (text, links) in lines {
+        let cut = dedent.min(indent(&text));
+        let offset = code.len();
+        code.push_str(&text[cut..]);
+        code.push('\n');
+        for link in links {
+            if link.range.start >= cut {
+                all_links.push(highlight::Link {
+                    range: link.range.start - cut + offset..link.range.end - cut + offset,
+                    ref_id: link.ref_id,
+                });
+            }
+        }
+    }
+    highlight::highlight_with_links(code.trim_end_matches('\n'), language, &all_links)
+        .replace('\n', "<br/>")
+}
+
+/// The plain text of a code block, with `<sp/>` as spaces. Records where `<ref>`s are.
+fn collect_code_text(node: Node, text: &mut String, links: &mut Vec<highlight::Link>) {
+    for child in node.children() {
+        match child.tag_name().name() {
+            "" => text.push_str(child.text().unwrap_or_default()),
+            "sp" => text.push(' '),
+            "ref" => {
+                let start = text.len();
+                collect_code_text(child, text, links);
+                if let Some(ref_id) = child.attribute("refid") {
+                    links.push(highlight::Link {
+                        range: start..text.len(),
+                        ref_id: ref_id.to_owned(),
+                    });
+                }
+            }
+            _ => collect_code_text(child, text, links),
+        }
+    }
 }
 
 fn capitalize_first_letter(s: &str) -> String {
@@ -706,6 +1602,10 @@ fn capitalize_first_letter(s: &str) -> String {
 pub trait NodeExt<'n1, 'n2> {
     fn get_child<'a>(&'a self, tag: &str) -> Option<Node<'n1, 'n2>>;
     fn get_child_value<'a>(&'a self, tag: &str) -> Option<&'a str>;
+    /// Like `get_child`, but a missing child is an error.
+    fn child(&self, tag: &str) -> Result<Node<'n1, 'n2>, DoxyError>;
+    fn child_value(&self, tag: &str) -> Result<&'n1 str, DoxyError>;
+    fn attr(&self, name: &str) -> Result<&'n1 str, DoxyError>;
 }
 impl<'n1, 'n2> NodeExt<'n1, 'n2> for Node<'n1, 'n2> {
     fn get_child<'a>(&'a self, tag: &str) -> Option<Node<'n1, 'n2>> {
@@ -721,4 +1621,790 @@ impl<'n1, 'n2> NodeExt<'n1, 'n2> for Node<'n1, 'n2> {
     fn get_child_value<'a>(&'a self, tag: &str) -> Option<&'a str> {
         self.get_child(tag).and_then(|n| n.text())
     }
+    fn child(&self, tag: &str) -> Result<Node<'n1, 'n2>, DoxyError> {
+        self.get_child(tag)
+            .ok_or_else(|| DoxyError::missing_element(*self, tag))
+    }
+    fn child_value(&self, tag: &str) -> Result<&'n1 str, DoxyError> {
+        self.child(tag)?
+            .text()
+            .ok_or_else(|| DoxyError::missing_element(*self, tag))
+    }
+    fn attr(&self, name: &str) -> Result<&'n1 str, DoxyError> {
+        self.attribute(name)
+            .ok_or_else(|| DoxyError::missing_attribute(*self, name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(protections: &[Protection]) -> ParseOptions {
+        ParseOptions {
+            inline_scopes: true,
+            protections: protections.to_vec(),
+            math: MathBackend::Mathml,
+        }
+    }
+
+    fn context(options: &ParseOptions) -> Context<'_> {
+        Context {
+            has_math: false,
+            options,
+            inputs: BTreeMap::new(),
+            warnings: vec![],
+        }
+    }
+
+    /// A function `memberdef` of the compound `classA`.
+    fn function(anchor: &str, prot: &str, name: &str, argsstring: &str) -> String {
+        format!(
+            "<memberdef kind=\"function\" id=\"classA_1{}\" prot=\"{}\" static=\"no\" \
+             virt=\"non-virtual\"><type>void</type><name>{}</name>\
+             <argsstring>{}</argsstring></memberdef>",
+            anchor, prot, name, argsstring
+        )
+    }
+
+    /// Parses the sections of a compound with the given memberdefs.
+    fn parse(memberdefs: &str, protections: &[Protection]) -> (Vec<Section>, Vec<DoxyError>) {
+        let xml = format!(
+            "<compounddef id=\"classA\" kind=\"class\"><sectiondef kind=\"public-func\">{}\
+             </sectiondef></compounddef>",
+            memberdefs
+        );
+        let doc = Document::parse(&xml).unwrap();
+        let options = options(protections);
+        let mut context = context(&options);
+        let sections = parse_sections(doc.root_element(), &mut context, |_| Ok(true)).unwrap();
+        (sections, context.warnings)
+    }
+
+    fn member_names(sections: &[Section]) -> Vec<&str> {
+        sections
+            .iter()
+            .flat_map(|s| s.members())
+            .map(|m| m.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn members_of_unknown_kinds_are_skipped_with_a_warning() {
+        let memberdefs = format!(
+            "{}<memberdef kind=\"slot\" id=\"classA_1s\" prot=\"public\"><type>void</type>\
+             <name>onClicked</name></memberdef>{}",
+            function("a", "public", "before", "()"),
+            function("b", "public", "after", "()")
+        );
+        let (sections, warnings) = parse(&memberdefs, &[Protection::Public]);
+        assert_eq!(member_names(&sections), ["before", "after"]);
+        assert!(matches!(
+            warnings.as_slice(),
+            [DoxyError::UnsupportedKind { kind, .. }] if kind == "slot"
+        ));
+    }
+
+    #[test]
+    fn inherited_members_leave_out_reimplemented_and_hidden_ones() {
+        let xml = "<compounddef id=\"classD\" kind=\"class\">\
+            <basecompoundref refid=\"classB\" prot=\"public\" virt=\"non-virtual\">B\
+            </basecompoundref>\
+            <sectiondef kind=\"public-func\">\
+              <memberdef kind=\"function\" id=\"classD_1f\" prot=\"public\">\
+                <name>f</name><reimplements refid=\"classB_1f\">f</reimplements></memberdef>\
+              <memberdef kind=\"variable\" id=\"classD_1v\" prot=\"public\"><name>v</name>\
+              </memberdef>\
+            </sectiondef>\
+            <listofallmembers>\
+              <member refid=\"classD_1f\" prot=\"public\"><name>f</name></member>\
+              <member refid=\"classB_1f\" prot=\"public\"><name>f</name></member>\
+              <member refid=\"classB_1f2\" prot=\"public\"><name>f</name></member>\
+              <member refid=\"classB_1v\" prot=\"public\"><name>v</name></member>\
+              <member refid=\"classB_1g\" prot=\"public\"><name>g</name></member>\
+              <member refid=\"classB_1h\" prot=\"private\"><name>h</name></member>\
+            </listofallmembers></compounddef>";
+        let doc = Document::parse(xml).unwrap();
+        let options = options(&[Protection::Public]);
+        let context = context(&options);
+        let groups = parse_inherited_members(doc.root_element(), &context).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].ref_id, "classB");
+        assert_eq!(groups[0].name, "<a href=\"refid://classB\">B</a>");
+        // the overload of f that D doesn't reimplement is still reachable through D
+        let members: Vec<&str> = groups[0]
+            .members
+            .iter()
+            .map(|m| m.ref_id.as_str())
+            .collect();
+        assert_eq!(members, ["classB_1f2", "classB_1g"]);
+    }
+
+    #[test]
+    fn inherited_members_are_grouped_by_the_declaring_class() {
+        let xml = "<compounddef id=\"ns_1_1classD\" kind=\"class\">\
+            <basecompoundref refid=\"ns_1_1classB\" prot=\"public\" virt=\"non-virtual\">B\
+            </basecompoundref>\
+            <inheritancegraph>\
+              <node id=\"1\"><label>ns::D</label><link refid=\"ns_1_1classD\"/></node>\
+              <node id=\"2\"><label>ns::B</label><link refid=\"ns_1_1classB\"/></node>\
+              <node id=\"3\"><label>Base&lt;T&gt;</label><link refid=\"classBase\"/></node>\
+            </inheritancegraph>\
+            <listofallmembers>\
+              <member refid=\"classBase_1a\" prot=\"public\"><name>a</name></member>\
+              <member refid=\"ns_1_1classB_1b\" prot=\"protected\"><name>b</name></member>\
+              <member refid=\"classBase_1c\" prot=\"protected\"><name>operator&lt;</name>\
+              </member>\
+              <member refid=\"classUndocumented_1d\" prot=\"public\"><name>d</name></member>\
+            </listofallmembers></compounddef>";
+        let doc = Document::parse(xml).unwrap();
+        let with_protected = options(&[Protection::Public, Protection::Protected]);
+        let groups =
+            parse_inherited_members(doc.root_element(), &context(&with_protected)).unwrap();
+        let summary: Vec<(&str, Vec<(&str, &str)>)> = groups
+            .iter()
+            .map(|g| {
+                let members = g
+                    .members
+                    .iter()
+                    .map(|m| (m.name.as_str(), m.protection.as_str()))
+                    .collect();
+                (g.name.as_str(), members)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "<a href=\"refid://classBase\">Base&lt;T&gt;</a>",
+                    vec![
+                        ("<a href=\"refid://classBase_1a\">a</a>", "public"),
+                        (
+                            "<a href=\"refid://classBase_1c\">operator&lt;</a>",
+                            "protected"
+                        ),
+                    ]
+                ),
+                (
+                    "<a href=\"refid://ns_1_1classB\">B</a>",
+                    vec![("<a href=\"refid://ns_1_1classB_1b\">b</a>", "protected")]
+                ),
+                (
+                    "<a href=\"refid://classUndocumented\">classUndocumented</a>",
+                    vec![("<a href=\"refid://classUndocumented_1d\">d</a>", "public")]
+                ),
+            ]
+        );
+
+        let public_only = options(&[Protection::Public]);
+        let groups = parse_inherited_members(doc.root_element(), &context(&public_only)).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].members.len(), 1);
+    }
+
+    #[test]
+    fn overloads_are_grouped_by_name() {
+        let memberdefs = format!(
+            "{}{}{}{}{}",
+            function("a", "public", "f", "(int)"),
+            function("b", "public", "g", "()"),
+            function("c", "public", "f", "(double)"),
+            function("d", "public", "operator==", "(const A &amp;)"),
+            function("e", "public", "operator==", "(int)")
+        );
+        let (sections, _) = parse(&memberdefs, &[Protection::Public]);
+        let sets: Vec<(&str, &str, Vec<&str>)> = sections[0]
+            .overload_sets
+            .iter()
+            .map(|set| {
+                let ref_ids = set.members.iter().map(|m| m.ref_id.as_str()).collect();
+                (set.name.as_str(), set.anchor.as_str(), ref_ids)
+            })
+            .collect();
+        assert_eq!(
+            sets,
+            [
+                ("f", "classA-f", vec!["classA_1a", "classA_1c"]),
+                ("g", "classA-g", vec!["classA_1b"]),
+                (
+                    "operator==",
+                    "classA-operator.3d.3d",
+                    vec!["classA_1d", "classA_1e"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn overload_anchors_escape_operator_characters() {
+        assert_eq!(overload_anchor("classA", "size_t"), "classA-size_t");
+        assert_eq!(
+            overload_anchor("classA", "operator()"),
+            "classA-operator.28.29"
+        );
+        assert_eq!(
+            overload_anchor("classA", "operator<<"),
+            "classA-operator.3c.3c"
+        );
+        assert_eq!(overload_anchor("classA", "~A"), "classA-.7eA");
+        // escapes can't make different names collide
+        assert_ne!(
+            overload_anchor("classA", "operator."),
+            overload_anchor("classA", "operator.2e")
+        );
+    }
+
+    #[test]
+    fn briefs_and_details_are_kept_apart() {
+        let memberdefs = "<memberdef kind=\"enum\" id=\"classA_1e\" prot=\"public\">\
+            <type/><name>E</name>\
+            <briefdescription> <para>Brief.</para> </briefdescription>\
+            <detaileddescription><para>Details.</para></detaileddescription>\
+            <enumvalue id=\"classA_1ev\" prot=\"public\"><name>V</name>\
+            <briefdescription><para>Value.</para></briefdescription>\
+            <detaileddescription/></enumvalue></memberdef>";
+        let (sections, _) = parse(memberdefs, &[Protection::Public]);
+        let member = sections[0].members().next().unwrap();
+        assert_eq!(member.brief, "<p>Brief.</p>");
+        assert_eq!(member.details, "<p>Details.</p>");
+        assert_eq!(member.description, "<p>Brief.</p><p>Details.</p>");
+        let value = &member.enum_values[0];
+        assert_eq!(value.brief, "<p>Value.</p>");
+        assert_eq!(value.details, "");
+        assert_eq!(value.description, "<p>Value.</p>");
+
+        let xml_dir = crate::test_dir("page-descriptions");
+        write_compound(
+            &xml_dir,
+            "guide",
+            "page",
+            "<compoundname>guide</compoundname><title>Guide</title>\
+             <detaileddescription><para>Details.</para></detaileddescription>",
+        );
+        let page = parse_compound_page(&xml_dir, "guide", &options(&[Protection::Public])).unwrap();
+        assert_eq!(page.brief, "");
+        assert_eq!(page.details, "<p>Details.</p>");
+        assert_eq!(page.description, page.details);
+    }
+
+    /// The rendered prefix, suffix and assignment of a memberdef with the given attributes and
+    /// argsstring, without the keyword spans.
+    fn qualifiers(kind: &str, attributes: &str, argsstring: &str) -> (String, String, String) {
+        let xml = format!(
+            "<memberdef kind=\"{}\" {}><argsstring>{}</argsstring></memberdef>",
+            kind, attributes, argsstring
+        );
+        let doc = Document::parse(&xml).unwrap();
+        let (prefix, suffix, assignment) =
+            render_qualifiers(&parse_qualifiers(doc.root_element()).unwrap());
+        let strip = |s: String| {
+            s.replace("<span class=\"keyword\">", "")
+                .replace("</span>", "")
+        };
+        (strip(prefix), strip(suffix), strip(assignment))
+    }
+
+    fn rendered(prefix: &str, suffix: &str, assignment: &str) -> (String, String, String) {
+        (prefix.to_owned(), suffix.to_owned(), assignment.to_owned())
+    }
+
+    #[test]
+    fn qualifiers_come_from_attributes() {
+        assert_eq!(
+            qualifiers(
+                "function",
+                "static=\"yes\" explicit=\"yes\" constexpr=\"yes\" virt=\"non-virtual\"",
+                "()"
+            ),
+            rendered("static explicit constexpr ", "", "")
+        );
+        assert_eq!(
+            qualifiers("function", "const=\"yes\" noexcept=\"yes\"", "()"),
+            rendered("", " const noexcept", "")
+        );
+        assert_eq!(
+            qualifiers("function", "virt=\"virtual\"", "()"),
+            rendered("virtual ", "", "")
+        );
+        assert_eq!(
+            qualifiers("variable", "static=\"yes\"", "[3]"),
+            rendered("static ", "", "")
+        );
+    }
+
+    #[test]
+    fn qualifiers_come_from_the_argsstring() {
+        assert_eq!(
+            qualifiers("function", "", "(int a=0) const noexcept override"),
+            rendered("", " const noexcept override", "")
+        );
+        assert_eq!(
+            qualifiers("function", "virt=\"virtual\"", "() final"),
+            rendered("", " final", "")
+        );
+        assert_eq!(
+            qualifiers("function", "virt=\"pure-virtual\"", "() const =0"),
+            rendered("virtual ", " const", " = 0")
+        );
+        assert_eq!(
+            qualifiers("function", "", "(const A &amp;)=delete"),
+            rendered("", "", " = delete")
+        );
+        assert_eq!(
+            qualifiers("function", "", "() = default"),
+            rendered("", "", " = default")
+        );
+        assert_eq!(
+            qualifiers("function", "", "() noexcept(false)"),
+            rendered("", "", "")
+        );
+        // only what follows the parameters counts
+        assert_eq!(
+            qualifiers(
+                "function",
+                "",
+                "(std::function&lt;void() const&gt; f, int x = 0)"
+            ),
+            rendered("", "", "")
+        );
+        // only functions have an argsstring with qualifiers
+        assert_eq!(
+            qualifiers("variable", "", " = delete"),
+            rendered("", "", "")
+        );
+    }
+
+    #[test]
+    fn errors_name_the_file() {
+        let xml_dir = crate::test_dir("parse-errors");
+        let options = options(&[Protection::Public]);
+
+        let error = parse_compound_page(&xml_dir, "missing", &options)
+            .err()
+            .unwrap();
+        assert!(matches!(error, DoxyError::Io { file, .. } if file == xml_dir.join("missing.xml")));
+
+        std::fs::write(xml_dir.join("bad.xml"), "<doxygen><compounddef>").unwrap();
+        let error = parse_compound_page(&xml_dir, "bad", &options)
+            .err()
+            .unwrap();
+        assert!(matches!(error, DoxyError::Xml { file, .. } if file == xml_dir.join("bad.xml")));
+
+        std::fs::write(
+            xml_dir.join("untitled.xml"),
+            "<doxygen><compounddef id=\"untitled\" kind=\"page\"></compounddef></doxygen>",
+        )
+        .unwrap();
+        let error = parse_compound_page(&xml_dir, "untitled", &options)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{}:1:10: <compounddef> has no (unique) <title> child",
+                xml_dir.join("untitled.xml").display()
+            )
+        );
+    }
+
+    /// Writes `<ref_id>.xml` with a compounddef around `content`.
+    fn write_compound(xml_dir: &Path, ref_id: &str, kind: &str, content: &str) {
+        std::fs::write(
+            xml_dir.join(format!("{}.xml", ref_id)),
+            format!(
+                "<doxygen><compounddef id=\"{}\" kind=\"{}\">{}</compounddef></doxygen>",
+                ref_id, kind, content
+            ),
+        )
+        .unwrap();
+    }
+
+    /// A function `memberdef` declared in `file`.
+    fn located_function(id: &str, name: &str, file: &str) -> String {
+        format!(
+            "<memberdef kind=\"function\" id=\"{}\" prot=\"public\"><type>void</type>\
+             <name>{}</name><argsstring>()</argsstring><location file=\"{}\"/></memberdef>",
+            id, name, file
+        )
+    }
+
+    /// A header `a.h` declaring part of class `ns::A`, whose other part is in `b.h`.
+    fn write_scopes_fixture(xml_dir: &Path) {
+        write_compound(
+            xml_dir,
+            "a_8h",
+            "file",
+            "<compoundname>a.h</compoundname><innerclass refid=\"classns_1_1A\">ns::A\
+             </innerclass><innerclass refid=\"classundocumented\">U</innerclass>\
+             <location file=\"include/a.h\"/>",
+        );
+        write_compound(
+            xml_dir,
+            "classns_1_1A",
+            "class",
+            &format!(
+                "<compoundname>ns::A</compoundname><sectiondef kind=\"public-func\">{}{}\
+                 </sectiondef><location file=\"include/a.h\"/>",
+                located_function("classns_1_1A_1a", "inA", "include/a.h"),
+                located_function("classns_1_1A_1b", "inB", "include/b.h")
+            ),
+        );
+    }
+
+    #[test]
+    fn per_file_layout_inlines_the_parts_of_classes_declared_in_the_file() {
+        let xml_dir = crate::test_dir("per-file");
+        write_scopes_fixture(&xml_dir);
+        let file = parse_compound_file(&xml_dir, "a_8h", &options(&[Protection::Public])).unwrap();
+        // the undocumented class has no XML and is left out
+        assert_eq!(file.scopes.len(), 1);
+        assert_eq!(file.scopes[0].ref_id, "classns_1_1A");
+        assert_eq!(member_names(&file.scopes[0].sections), ["inA"]);
+        assert!(file.common.inputs.contains_key("classns_1_1A.xml"));
+    }
+
+    #[test]
+    fn per_scope_layout_gives_classes_pages_of_their_own() {
+        let xml_dir = crate::test_dir("per-scope");
+        write_scopes_fixture(&xml_dir);
+        let options = ParseOptions {
+            inline_scopes: false,
+            ..options(&[Protection::Public])
+        };
+        let file = parse_compound_file(&xml_dir, "a_8h", &options).unwrap();
+        assert!(file.scopes.is_empty());
+        let page = parse_compound_scope_page(&xml_dir, "classns_1_1A", &options).unwrap();
+        assert_eq!(page.common.title, "ns::A");
+        assert_eq!(page.common.source, "include/a.h");
+        assert_eq!(member_names(&page.scope.sections), ["inA", "inB"]);
+    }
+
+    #[test]
+    fn scope_names_split_namespace_and_name() {
+        assert_eq!(
+            render_scope_name("ns::A<T>"),
+            "<span class=\"namespace_part\">ns::</span><span class=\"name_part\">&#8203;A&lt;T&gt;</span>"
+        );
+        assert_eq!(render_scope_name("A"), "<span class=\"name_part\">A</span>");
+    }
+
+    #[test]
+    fn file_scope_holds_the_members_the_file_owns() {
+        let xml_dir = crate::test_dir("file-scope");
+        write_compound(
+            &xml_dir,
+            "b_8h",
+            "file",
+            &format!(
+                "<compoundname>b.h</compoundname><sectiondef kind=\"func\">{}{}</sectiondef>\
+                 <briefdescription><para>Helpers.</para></briefdescription>\
+                 <location file=\"include/b.h\"/>",
+                located_function("b_8h_1f", "free", "include/b.h"),
+                // doxygen repeats namespace members on the file
+                located_function("namespacens_1g", "inNamespace", "include/b.h")
+            ),
+        );
+        let file = parse_compound_file(&xml_dir, "b_8h", &options(&[Protection::Public])).unwrap();
+        assert_eq!(file.scopes.len(), 1);
+        let scope = &file.scopes[0];
+        assert_eq!(
+            (scope.kind.as_str(), scope.ref_id.as_str()),
+            ("file", "b_8h")
+        );
+        assert_eq!(
+            scope.name,
+            "<span class=\"kind_part\">file</span> <span class=\"name_part\">b.h</span>"
+        );
+        assert_eq!(scope.brief, "<p>Helpers.</p>");
+        assert_eq!(member_names(&scope.sections), ["free"]);
+    }
+
+    #[test]
+    fn defines_render_parameters_and_replacement() {
+        let memberdefs = "<memberdef kind=\"define\" id=\"classA_1m\" prot=\"public\">\
+            <name>MAX</name><param><defname>a</defname></param><param><defname>b</defname>\
+            </param><initializer>((a) &gt; (b) ? (a) : (b))</initializer></memberdef>\
+            <memberdef kind=\"define\" id=\"classA_1n\" prot=\"public\"><name>NOARGS</name>\
+            <param></param></memberdef>\
+            <memberdef kind=\"define\" id=\"classA_1v\" prot=\"public\"><name>VERSION</name>\
+            <initializer>3</initializer></memberdef>";
+        let (sections, warnings) = parse(memberdefs, &[Protection::Public]);
+        assert!(warnings.is_empty());
+        let definitions: Vec<&str> = sections[0]
+            .members()
+            .map(|m| m.definition.as_str())
+            .collect();
+        let keyword = "<span class=\"keyword\">#define</span>";
+        assert_eq!(
+            definitions,
+            [
+                format!(
+                    "{} <span class=\"member_name\">MAX</span>(<span class=\"declname\">a</span>, \
+                     <span class=\"declname\">b</span>) \
+                     <span class=\"defval\">((a) &gt; (b) ? (a) : (b))</span>",
+                    keyword
+                ),
+                format!("{} <span class=\"member_name\">NOARGS</span>()", keyword),
+                format!(
+                    "{} <span class=\"member_name\">VERSION</span> <span class=\"defval\">3</span>",
+                    keyword
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn members_are_filtered_by_protection() {
+        let memberdefs = format!(
+            "{}{}{}<memberdef kind=\"friend\" id=\"classA_1fr\" prot=\"public\">\
+             <type>class</type><name>B</name></memberdef>",
+            function("a", "public", "pub", "()"),
+            function("b", "protected", "prot", "()"),
+            function("c", "private", "priv", "()")
+        );
+        let (sections, _) = parse(&memberdefs, &[Protection::Public]);
+        assert_eq!(member_names(&sections), ["pub"]);
+        let (sections, _) = parse(&memberdefs, &[Protection::Public, Protection::Protected]);
+        assert_eq!(member_names(&sections), ["pub", "prot"]);
+        // a section without selected members is dropped
+        let (sections, _) = parse(&memberdefs, &[Protection::Package]);
+        assert!(sections.is_empty());
+    }
+
+    #[test]
+    fn class_refs_are_filtered_by_protection() {
+        let doc = Document::parse(
+            "<compounddef id=\"classD\"><basecompoundref refid=\"classA\" prot=\"public\" \
+             virt=\"virtual\">A</basecompoundref><basecompoundref prot=\"private\" \
+             virt=\"non-virtual\">std::string</basecompoundref></compounddef>",
+        )
+        .unwrap();
+        let options = options(&[Protection::Public]);
+        let bases =
+            parse_class_refs(doc.root_element(), "basecompoundref", &context(&options)).unwrap();
+        assert_eq!(bases.len(), 1);
+        assert_eq!(bases[0].ref_id.as_deref(), Some("classA"));
+        assert!(bases[0].is_virtual);
+    }
+
+    #[test]
+    fn class_refs_link_documented_classes() {
+        let doc = Document::parse(
+            "<compounddef id=\"classD\"><derivedcompoundref refid=\"classE\" prot=\"protected\" \
+             virt=\"non-virtual\">E</derivedcompoundref><basecompoundref prot=\"public\" \
+             virt=\"non-virtual\">std::vector&lt;int&gt;</basecompoundref></compounddef>",
+        )
+        .unwrap();
+        let options = options(&[Protection::Public, Protection::Protected]);
+        let context = context(&options);
+        let derived = parse_class_refs(doc.root_element(), "derivedcompoundref", &context).unwrap();
+        assert_eq!(derived.len(), 1);
+        assert_eq!(derived[0].name, "<a href=\"refid://classE\">E</a>");
+        assert_eq!(derived[0].protection, "protected");
+        assert!(!derived[0].is_virtual);
+        let bases = parse_class_refs(doc.root_element(), "basecompoundref", &context).unwrap();
+        assert_eq!(bases[0].ref_id, None);
+        assert_eq!(bases[0].name, "std::vector&lt;int&gt;");
+    }
+
+    #[test]
+    fn inheritance_graph_takes_direct_edges_from_the_compoundrefs() {
+        // Root <- A <-(virtual) D <- E, and D privately derives from P, which derives from Q
+        let node = |id: &str, label: &str, ref_id: &str, children: &[(&str, &str)]| {
+            let children = children
+                .iter()
+                .map(|(id, relation)| {
+                    format!("<childnode refid=\"{}\" relation=\"{}\"/>", id, relation)
+                })
+                .collect::<String>();
+            format!(
+                "<node id=\"{}\"><label>{}</label><link refid=\"{}\"/>{}</node>",
+                id, label, ref_id, children
+            )
+        };
+        let xml = format!(
+            "<compounddef id=\"classD\">\
+             <basecompoundref refid=\"classA\" prot=\"public\" virt=\"virtual\">A\
+             </basecompoundref>\
+             <basecompoundref refid=\"classP\" prot=\"private\" virt=\"non-virtual\">P\
+             </basecompoundref>\
+             <derivedcompoundref refid=\"classE\" prot=\"public\" virt=\"non-virtual\">E\
+             </derivedcompoundref>\
+             <inheritancegraph>{}{}{}{}{}{}</inheritancegraph></compounddef>",
+            node(
+                "1",
+                "D",
+                "classD",
+                &[("2", "public-inheritance"), ("4", "private-inheritance")]
+            ),
+            node("2", "A", "classA", &[("3", "public-inheritance")]),
+            node("3", "Root", "classRoot", &[]),
+            node("4", "P", "classP", &[("5", "public-inheritance")]),
+            node("5", "Q", "classQ", &[]),
+            node("6", "E", "classE", &[("1", "public-inheritance")]),
+        );
+        let doc = Document::parse(&xml).unwrap();
+        let inheritancegraph = doc.root_element().get_child("inheritancegraph").unwrap();
+        let graph_with = |protections: &[Protection]| {
+            let options = options(protections);
+            parse_inheritancegraph(
+                doc.root_element(),
+                inheritancegraph,
+                "classD",
+                &context(&options),
+            )
+            .unwrap()
+        };
+        let edges = |graph: &diagram::Graph| {
+            let mut edges = graph
+                .edges
+                .iter()
+                .map(|e| {
+                    (
+                        graph.nodes[e.derived].label.clone(),
+                        graph.nodes[e.base].label.clone(),
+                        e.protection.clone(),
+                        e.is_virtual,
+                    )
+                })
+                .collect::<Vec<_>>();
+            edges.sort();
+            edges
+        };
+        let edge = |derived: &str, base: &str, protection: &str, is_virtual| {
+            (
+                derived.to_owned(),
+                base.to_owned(),
+                protection.to_owned(),
+                is_virtual,
+            )
+        };
+
+        let graph = graph_with(&[Protection::Public]);
+        assert_eq!(
+            edges(&graph),
+            [
+                edge("A", "Root", "public", false),
+                edge("D", "A", "public", true),
+                edge("E", "D", "public", false),
+            ]
+        );
+        let labels = graph
+            .nodes
+            .iter()
+            .map(|n| n.label.as_str())
+            .collect::<Vec<_>>();
+        assert!(!labels.contains(&"P") && !labels.contains(&"Q"));
+        assert!(graph.nodes.iter().any(|n| n.label == "D" && n.is_current));
+
+        let graph = graph_with(&[Protection::Public, Protection::Private]);
+        assert_eq!(edges(&graph).len(), 5);
+        assert!(edges(&graph).contains(&edge("D", "P", "private", false)));
+    }
+
+    /// Parses a `para` with the given content. Returns the HTML, whether it has math and the
+    /// warnings.
+    fn para(content: &str, math: MathBackend) -> (String, bool, Vec<DoxyError>) {
+        let options = ParseOptions {
+            math,
+            ..options(&[Protection::Public])
+        };
+        let xml = format!("<para>{}</para>", content);
+        let doc = Document::parse(&xml).unwrap();
+        let mut context = context(&options);
+        let html = parse_text(doc.root_element(), &mut context).unwrap();
+        (html, context.has_math, context.warnings)
+    }
+
+    #[test]
+    fn formulas_are_rendered_at_build_time() {
+        let (html, has_math, _) = para("<formula id=\"0\">$a&lt;b$</formula>", MathBackend::Mathml);
+        assert!(has_math);
+        assert!(html.starts_with("<math display=\"inline\">"), "{}", html);
+        assert!(!html.contains("\\("));
+
+        // the TeX backend leaves them to a renderer the theme brings
+        let (html, has_math, _) = para("<formula id=\"0\">$a&lt;b$</formula>", MathBackend::Tex);
+        assert!(has_math);
+        assert_eq!(html, "\\(a&lt;b\\)");
+        let (html, _, _) = para("<formula id=\"0\">\\[x\\]</formula>", MathBackend::Tex);
+        assert_eq!(html, "<span class=\"math-display\">\\[x\\]</span>");
+
+        let (_, has_math, _) = para("no math", MathBackend::Mathml);
+        assert!(!has_math);
+    }
+
+    #[test]
+    fn display_formulas_and_environments_get_their_own_block() {
+        let (html, _, _) = para("<formula id=\"0\">\\[x\\]</formula>", MathBackend::Mathml);
+        assert!(
+            html.starts_with("<span class=\"math-display\"><math display=\"block\">"),
+            "{}",
+            html
+        );
+        let (html, _, warnings) = para(
+            "<formula id=\"0\">\\begin{align}a &amp;= b\\end{align}</formula>",
+            MathBackend::Mathml,
+        );
+        assert!(html.contains("<mtable"), "{}", html);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn invalid_formulas_are_reported() {
+        let (html, _, warnings) = para(
+            "<formula id=\"0\">$\\nope&lt;$</formula>",
+            MathBackend::Mathml,
+        );
+        assert_eq!(html, "<code class=\"math-error\">\\nope&lt;</code>");
+        assert!(matches!(
+            warnings.as_slice(),
+            [DoxyError::InvalidFormula { formula, .. }] if formula == "$\\nope<$"
+        ));
+    }
+
+    #[test]
+    fn listings_with_a_known_language_are_highlighted_natively() {
+        let (html, _, _) = para(
+            "<programlisting filename=\".py\"><codeline><highlight class=\"normal\">\
+             <sp/><sp/>def<sp/><ref refid=\"f\" kindref=\"member\">f</ref>():</highlight>\
+             </codeline><codeline><highlight class=\"normal\"><sp/><sp/><sp/><sp/>pass\
+             </highlight></codeline></programlisting>",
+            MathBackend::Mathml,
+        );
+        // dedented, with the links kept
+        assert_eq!(
+            html,
+            "<pre class=\"programlisting\"><span class=\"highlight-keyword\">def</span>\
+             <span class=\"highlight-normal\"> <a href=\"refid://f\">f</a>():<br/>  </span>\
+             <span class=\"highlight-keywordflow\">pass</span></pre>"
+        );
+    }
+
+    #[test]
+    fn other_listings_keep_the_highlighting_of_doxygen() {
+        let (html, _, _) = para(
+            "<programlisting><codeline><highlight class=\"normal\"><sp/><sp/>a</highlight>\
+             </codeline><codeline><highlight class=\"normal\"><sp/><sp/><sp/>b</highlight>\
+             </codeline></programlisting>",
+            MathBackend::Mathml,
+        );
+        assert_eq!(
+            html,
+            "<pre class=\"programlisting\"><span class=\"highlight-normal\">a</span><br/>\
+             <span class=\"highlight-normal\">&nbsp;b</span><br/></pre>"
+        );
+    }
+
+    #[test]
+    fn verbatim_blocks_get_generic_highlighting() {
+        let (html, _, _) = para("<verbatim>x = \"a\" # note</verbatim>", MathBackend::Mathml);
+        assert_eq!(
+            html,
+            "<pre><span class=\"highlight-normal\">x = </span>\
+             <span class=\"highlight-stringliteral\">&quot;a&quot;</span>\
+             <span class=\"highlight-normal\"> </span>\
+             <span class=\"highlight-comment\"># note</span></pre>"
+        );
+    }
 }
@@ -0,0 +1,683 @@
+use crate::Compound;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+// The indices are split into shards that the browser loads on demand as scripts. Scripts instead
+// of JSON files because `fetch` does not work for pages opened via file://. Each script calls
+// searchDataLoaded("<path relative to the output root>", <data>).
+//
+// Symbols, sharded by the first letter of the unqualified name. Exact and prefix matches only need
+// the query's shard. Substring and fuzzy matches need all of them, so the page only looks for
+// those when the user asks it to:
+//   search/keys.js: [key, ...] of the symbol shards
+//   search/<key>.js: [[qualified name, kind, brief, href], ...]
+// Full text, an inverted index over the stemmed words of all descriptions:
+//   search/text/t<key>.js: [number of documents, {term: [doc id, term count, doc id, ...]}]
+//   search/text/d<n>.js: [[title, kind, href, number of terms, plain text], ...]
+//     holding documents n * DOCS_PER_SHARD and up
+
+struct Entry {
+    qualified_name: String,
+    kind: String,
+    brief: String,
+    href: String,
+}
+
+pub fn write_search_index(
+    html_dir: &Path,
+    compounds: &[Compound],
+    ref_to_path: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let mut entries = vec![];
+    let mut seen = HashSet::new();
+    let href = |ref_id: &str| ref_to_path.get(ref_id).cloned();
+    let mut push = |href: Option<String>, qualified_name: String, kind: &str, brief: String| {
+        // namespaces show up once per file in the per-file layout
+        if !seen.insert((qualified_name.clone(), kind.to_owned())) {
+            return;
+        }
+        if let Some(href) = href {
+            entries.push(Entry {
+                qualified_name,
+                kind: kind.to_owned(),
+                brief,
+                href,
+            });
+        }
+    };
+
+    for compound in compounds {
+        let scopes = match compound {
+            Compound::File(file) => {
+                let common = &file.common;
+                push(
+                    href(&common.ref_id),
+                    common.title.clone(),
+                    "file",
+                    String::new(),
+                );
+                file.scopes.iter().collect::<Vec<_>>()
+            }
+            Compound::Page(page) => {
+                let common = &page.common;
+                let brief = brief_from_html(&page.brief, &page.description);
+                push(href(&common.ref_id), common.title.clone(), "page", brief);
+                vec![]
+            }
+            Compound::Scope(scope_page) => vec![&scope_page.scope],
+        };
+        for scope in scopes {
+            let prefix = if scope.kind == "file" {
+                String::new()
+            } else {
+                push(
+                    href(&scope.ref_id),
+                    scope.qualified_name.clone(),
+                    &scope.kind,
+                    brief_from_html(&scope.brief, &scope.description),
+                );
+                format!("{}::", scope.qualified_name)
+            };
+            for section in &scope.sections {
+                for set in &section.overload_sets {
+                    let qualified_name = format!("{}{}", prefix, set.name);
+                    for member in &set.members {
+                        for enum_value in &member.enum_values {
+                            push(
+                                href(&enum_value.ref_id),
+                                format!("{}::{}", qualified_name, enum_value.name),
+                                "enumvalue",
+                                brief_from_html(&enum_value.brief, &enum_value.description),
+                            );
+                        }
+                    }
+                    // one entry per overload set, linking to the whole set
+                    let first = &set.members[0];
+                    let set_href = match set.members.len() {
+                        1 => href(&first.ref_id),
+                        _ => href(&first.ref_id).map(|href| {
+                            let page = href.split('#').next().unwrap_or_default();
+                            format!("{}#{}", page, set.anchor)
+                        }),
+                    };
+                    let brief = brief_from_html(&first.brief, &first.description);
+                    push(set_href, qualified_name, &first.kind, brief);
+                }
+            }
+        }
+    }
+
+    let mut shards = BTreeMap::<String, Vec<Entry>>::new();
+    for entry in entries {
+        let name = entry.qualified_name.rsplit("::").next().unwrap_or_default();
+        shards.entry(shard_key(name)).or_default().push(entry);
+    }
+
+    let search_dir = html_dir.join("search");
+    std::fs::create_dir_all(&search_dir)?;
+    let keys: Vec<&str> = shards.keys().map(|k| k.as_str()).collect();
+    write_data_script(html_dir, "search/keys.js", &keys.into())?;
+    let mut written: HashSet<String> = shards.keys().map(|key| format!("{}.js", key)).collect();
+    written.insert("keys.js".to_owned());
+    for (key, mut entries) in shards {
+        entries.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+        let mut doc = json::array![];
+        for entry in entries {
+            doc.push(json::array![
+                entry.qualified_name,
+                entry.kind,
+                entry.brief,
+                entry.href
+            ])
+            .unwrap();
+        }
+        write_data_script(html_dir, &format!("search/{}.js", key), &doc)?;
+    }
+    remove_stale_files(&search_dir, &written)
+}
+
+/// Removes the files in `dir` that this run did not write, like shards of a larger index.
+/// Subdirectories are left alone.
+fn remove_stale_files(dir: &Path, written: &HashSet<String>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_file() && !written.contains(&name) {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_data_script(html_dir: &Path, path: &str, data: &json::JsonValue) -> std::io::Result<()> {
+    let f = std::fs::File::create(html_dir.join(path))?;
+    let mut f = std::io::BufWriter::new(f);
+    write!(f, "searchDataLoaded(\"{}\",", path)?;
+    data.write(&mut f)?;
+    f.write_all(b");")
+}
+
+const DOCS_PER_SHARD: usize = 500;
+/// Longer texts are cut off in the document shards. They are only used for snippets.
+const MAX_SNIPPET_TEXT: usize = 5000;
+
+struct TextDocument {
+    title: String,
+    kind: &'static str,
+    href: String,
+    text: String,
+}
+
+pub fn write_text_index(
+    html_dir: &Path,
+    compounds: &[Compound],
+    ref_to_path: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let mut documents = vec![];
+    let mut push = |ref_id: &str, title: String, kind: &'static str, html: &str| {
+        let text = strip_html(html);
+        if text.is_empty() {
+            return;
+        }
+        if let Some(href) = ref_to_path.get(ref_id) {
+            documents.push(TextDocument {
+                title,
+                kind,
+                href: href.clone(),
+                text,
+            });
+        }
+    };
+    for compound in compounds {
+        let scopes = match compound {
+            Compound::File(file) => file.scopes.iter().collect::<Vec<_>>(),
+            Compound::Page(page) => {
+                push(
+                    &page.common.ref_id,
+                    page.common.title.clone(),
+                    "page",
+                    &page.description,
+                );
+                vec![]
+            }
+            Compound::Scope(scope_page) => vec![&scope_page.scope],
+        };
+        for scope in scopes {
+            let prefix = if scope.kind == "file" {
+                String::new()
+            } else {
+                format!("{}::", scope.qualified_name)
+            };
+            for section in &scope.sections {
+                for member in section.members() {
+                    let qualified_name = format!("{}{}", prefix, member.name);
+                    for enum_value in &member.enum_values {
+                        push(
+                            &enum_value.ref_id,
+                            format!("{}::{}", qualified_name, enum_value.name),
+                            "enumvalue",
+                            &enum_value.description,
+                        );
+                    }
+                    push(
+                        &member.ref_id,
+                        qualified_name,
+                        "member",
+                        &member.description,
+                    );
+                }
+            }
+        }
+    }
+
+    // term -> [(document, count)]
+    let mut postings = BTreeMap::<String, Vec<(usize, usize)>>::new();
+    let mut lengths = vec![];
+    for (id, document) in documents.iter().enumerate() {
+        let terms = tokenize(&document.text);
+        lengths.push(terms.len());
+        let mut counts = BTreeMap::<String, usize>::new();
+        for term in terms {
+            *counts.entry(term).or_default() += 1;
+        }
+        for (term, count) in counts {
+            postings.entry(term).or_default().push((id, count));
+        }
+    }
+
+    let text_dir = html_dir.join("search").join("text");
+    std::fs::create_dir_all(&text_dir)?;
+
+    let mut shards = BTreeMap::<String, json::JsonValue>::new();
+    for (term, documents) in postings {
+        let mut list = json::array![];
+        for (id, count) in documents {
+            list.push(id).unwrap();
+            list.push(count).unwrap();
+        }
+        let shard = shards
+            .entry(shard_key(&term))
+            .or_insert_with(json::JsonValue::new_object);
+        shard[term.as_str()] = list;
+    }
+    let mut written = HashSet::new();
+    for (key, terms) in shards {
+        let data = json::array![documents.len(), terms];
+        write_data_script(html_dir, &format!("search/text/t{}.js", key), &data)?;
+        written.insert(format!("t{}.js", key));
+    }
+
+    for (n, chunk) in documents.chunks(DOCS_PER_SHARD).enumerate() {
+        let mut data = json::array![];
+        for (i, document) in chunk.iter().enumerate() {
+            let text: String = document.text.chars().take(MAX_SNIPPET_TEXT).collect();
+            data.push(json::array![
+                document.title.as_str(),
+                document.kind,
+                document.href.as_str(),
+                lengths[n * DOCS_PER_SHARD + i],
+                text
+            ])
+            .unwrap();
+        }
+        write_data_script(html_dir, &format!("search/text/d{}.js", n), &data)?;
+        written.insert(format!("d{}.js", n));
+    }
+    remove_stale_files(&text_dir, &written)
+}
+
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any",
+    "are", "as", "at", "be", "because", "been", "before", "being", "below", "between", "both",
+    "but", "by", "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few",
+    "for", "from", "further", "had", "has", "have", "having", "he", "her", "here", "hers", "him",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself", "just", "may", "me",
+    "more", "most", "must", "my", "no", "nor", "not", "now", "of", "off", "on", "once", "only",
+    "or", "other", "our", "out", "over", "own", "same", "she", "should", "so", "some", "such",
+    "than", "that", "the", "their", "them", "then", "there", "these", "they", "this", "those",
+    "through", "to", "too", "under", "until", "up", "very", "was", "we", "were", "what", "when",
+    "where", "which", "while", "who", "whom", "why", "will", "with", "would", "you", "your",
+];
+
+/// Splits plain text into lower-case, stemmed words without stop words. Must match `tokenize` in
+/// static/script.js.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| word.len() >= 2)
+        .map(|word| word.to_ascii_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| stem(&word))
+        .collect()
+}
+
+/// A much reduced Porter stemmer: folds plurals, -ed and -ing forms and a trailing e. Must match
+/// `stem` in static/script.js.
+fn stem(word: &str) -> String {
+    fn is_vowel(c: u8) -> bool {
+        matches!(c, b'a' | b'e' | b'i' | b'o' | b'u' | b'y')
+    }
+
+    let mut w = word.as_bytes().to_vec();
+    if w.ends_with(b"sses") {
+        w.truncate(w.len() - 2);
+    } else if w.ends_with(b"ies") && w.len() > 4 {
+        w.truncate(w.len() - 3);
+        w.push(b'y');
+    } else if w.ends_with(b"s")
+        && !w.ends_with(b"ss")
+        && !w.ends_with(b"us")
+        && !w.ends_with(b"is")
+        && w.len() > 3
+    {
+        w.pop();
+    }
+
+    for suffix in [&b"ing"[..], &b"ed"[..]] {
+        if w.ends_with(suffix) {
+            let stem_len = w.len() - suffix.len();
+            if stem_len >= 3 && w[..stem_len].iter().any(|&c| is_vowel(c)) {
+                w.truncate(stem_len);
+                if w.ends_with(b"at") || w.ends_with(b"bl") || w.ends_with(b"iz") {
+                    w.push(b'e');
+                } else {
+                    let last = w[w.len() - 1];
+                    if last == w[w.len() - 2] && !is_vowel(last) && !b"lsz".contains(&last) {
+                        w.pop();
+                    }
+                }
+            }
+            break;
+        }
+    }
+
+    if w.ends_with(b"e") && w.len() > 4 {
+        w.pop();
+    }
+    String::from_utf8(w).unwrap()
+}
+
+/// Must match `shardKey` in static/script.js.
+fn shard_key(name: &str) -> String {
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphanumeric() => c.to_ascii_lowercase().to_string(),
+        _ => "_".to_owned(),
+    }
+}
+
+/// Plain text of the brief description, or without one the first sentence of the full
+/// description, for result lists.
+pub fn brief_from_html(brief: &str, description: &str) -> String {
+    let html = if brief.is_empty() { description } else { brief };
+    let text = strip_html(html);
+    let text = text.trim();
+    let end = text
+        .match_indices(". ")
+        .next()
+        .map(|(i, _)| i + 1)
+        .unwrap_or(text.len());
+    let brief = &text[..end];
+    if brief.chars().count() > 150 {
+        let mut brief: String = brief.chars().take(149).collect();
+        brief.push('…');
+        brief
+    } else {
+        brief.to_owned()
+    }
+}
+
+/// Drops all tags and decodes the entities the parser produces. Block-level tags become spaces so
+/// words from different paragraphs don't run together.
+pub fn strip_html(html: &str) -> String {
+    // closing inline tags leave a space in front of punctuation
+    to_text(html, true)
+        .replace(" .", ".")
+        .replace(" ,", ",")
+        .replace(" :", ":")
+}
+
+/// Like `strip_html`, but tags don't separate words. For code like signatures, whose markup only
+/// wraps parts of the text.
+pub fn strip_code_html(html: &str) -> String {
+    to_text(html, false).replace("( ", "(").replace(" )", ")")
+}
+
+fn to_text(html: &str, tags_separate_words: bool) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                if tags_separate_words && !text.ends_with(' ') {
+                    text.push(' ');
+                }
+            }
+            _ if in_tag => {}
+            _ => text.push(c),
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&#8203;", "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#x2F;", "/")
+        .replace("&amp;", "&");
+    let mut collapsed = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        if !collapsed.is_empty() {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(word);
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `js_matches_rust` checks that static/script.js produces the same stems.
+    const STEMS: &[(&str, &str)] = &[
+        ("classes", "class"),
+        ("libraries", "library"),
+        ("ties", "tie"),
+        ("glass", "glass"),
+        ("status", "status"),
+        ("analysis", "analysis"),
+        ("initialising", "initialis"),
+        ("running", "run"),
+        ("created", "creat"),
+        ("enabled", "enabl"),
+        ("realized", "realiz"),
+        ("falling", "fall"),
+        ("sing", "sing"),
+        ("used", "used"),
+        ("make", "make"),
+        ("compute", "comput"),
+        ("values", "valu"),
+    ];
+
+    #[test]
+    fn stems_match_table() {
+        for (word, stemmed) in STEMS {
+            assert_eq!(stem(word), *stemmed, "stem of {}", word);
+        }
+    }
+
+    #[test]
+    fn strip_html_joins_punctuation_but_strip_code_html_keeps_it() {
+        assert_eq!(
+            strip_html("<p>See <code>Foo</code>, then <b>bar</b>.</p>"),
+            "See Foo, then bar."
+        );
+        assert_eq!(
+            strip_code_html("int <b>f</b>(bool x) { return x ? (a) : (b); }"),
+            "int f(bool x) { return x ? (a) : (b); }"
+        );
+    }
+
+    #[test]
+    fn tokenize_splits_lowercases_and_drops_stop_words() {
+        assert_eq!(
+            tokenize("The Classes are running: x, IO_Buffer2 and std::vector<int>"),
+            ["class", "run", "io", "buffer2", "std", "vector", "int"]
+        );
+    }
+
+    /// The JS half of the text search, from the stop words to the end of `tokenize`.
+    fn js_tokenizer() -> &'static str {
+        let script = include_str!("../static/script.js");
+        let start = script.find("const stopWords").unwrap();
+        let tokenize = script.find("function tokenize(").unwrap();
+        let end = tokenize + script[tokenize..].find("\n}\n").unwrap() + 3;
+        &script[start..end]
+    }
+
+    /// The browser tokenizes queries with static/script.js, so it has to agree with the index.
+    /// Runs the JS in node on the stem table, every stop word and some sentences. Skipped if node
+    /// is not installed.
+    #[test]
+    fn js_matches_rust() {
+        let mut inputs: Vec<&str> = STEMS.iter().map(|(word, _)| *word).collect();
+        inputs.extend(STOP_WORDS);
+        inputs.extend([
+            "The Classes are running: x, IO_Buffer2 and std::vector<int>",
+            "Initialises the PASSES; re-enabled caching (see RFC-2616) for 3 hours",
+            "Ünïcode wörds are split like JS does",
+        ]);
+        let expected = json::JsonValue::from(
+            inputs
+                .iter()
+                .map(|input| json::JsonValue::from(tokenize(input)))
+                .collect::<Vec<_>>(),
+        );
+
+        let harness = format!(
+            "{}\nconsole.log(JSON.stringify({}.map(tokenize)));\n",
+            js_tokenizer(),
+            json::JsonValue::from(inputs.clone())
+        );
+        let file_name =
+            std::env::temp_dir().join(format!("doxyrs-tokenize-{}.js", std::process::id()));
+        std::fs::write(&file_name, harness).unwrap();
+        let output = std::process::Command::new("node").arg(&file_name).output();
+        std::fs::remove_file(&file_name).unwrap();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("node not found, not checking static/script.js");
+                return;
+            }
+            Err(e) => panic!("could not run node: {}", e),
+        };
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let actual = json::parse(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        for (i, input) in inputs.iter().enumerate() {
+            assert_eq!(actual[i], expected[i], "tokenize({:?})", input);
+        }
+    }
+
+    #[test]
+    fn shard_keys() {
+        assert_eq!(shard_key("Vector"), "v");
+        assert_eq!(shard_key("2d"), "2");
+        assert_eq!(shard_key("_private"), "_");
+        assert_eq!(shard_key("operator+"), "o");
+        assert_eq!(shard_key("Ärger"), "_");
+        assert_eq!(shard_key(""), "_");
+    }
+
+    #[test]
+    fn briefs_fall_back_to_the_first_sentence() {
+        assert_eq!(
+            brief_from_html("<p>Short.</p>", "<p>Long. More.</p>"),
+            "Short."
+        );
+        assert_eq!(brief_from_html("", "<p>Long. More.</p>"), "Long.");
+        let long = "x".repeat(200);
+        let brief = brief_from_html(&long, "");
+        assert_eq!(brief.chars().count(), 150);
+        assert!(brief.ends_with('…'));
+    }
+
+    /// Parses a class with two overloads of `f` and a `g` from XML.
+    fn parse_class(xml_dir: &Path) -> Compound {
+        let function = |anchor: &str, name: &str| {
+            format!(
+                "<memberdef kind=\"function\" id=\"classA_1{}\" prot=\"public\">\
+                 <type>void</type><name>{}</name><argsstring>()</argsstring>\
+                 <briefdescription><para>Does {}.</para></briefdescription></memberdef>",
+                anchor, name, anchor
+            )
+        };
+        std::fs::write(
+            xml_dir.join("classA.xml"),
+            format!(
+                "<doxygen><compounddef id=\"classA\" kind=\"class\"><compoundname>A\
+                 </compoundname><sectiondef kind=\"public-func\">{}{}{}</sectiondef>\
+                 </compounddef></doxygen>",
+                function("f1", "f"),
+                function("f2", "f"),
+                function("g", "g")
+            ),
+        )
+        .unwrap();
+        let options = crate::parser::ParseOptions {
+            inline_scopes: false,
+            protections: vec![crate::parser::Protection::Public],
+            math: Default::default(),
+        };
+        let page = crate::parser::parse_compound_scope_page(xml_dir, "classA", &options).unwrap();
+        Compound::Scope(Box::new(page))
+    }
+
+    /// The data of a shard script written by `write_data_script`.
+    fn read_data_script(html_dir: &Path, path: &str) -> json::JsonValue {
+        let script = std::fs::read_to_string(html_dir.join(path)).unwrap();
+        let prefix = format!("searchDataLoaded(\"{}\",", path);
+        json::parse(&script[prefix.len()..script.len() - 2]).unwrap()
+    }
+
+    #[test]
+    fn symbol_index_has_one_entry_per_overload_set() {
+        let dir = crate::test_dir("symbol-index");
+        let compounds = [parse_class(&dir)];
+        let ref_to_path: HashMap<String, String> = [
+            ("classA", "classA.html"),
+            ("classA_1f1", "classA.html#classA_1f1"),
+            ("classA_1f2", "classA.html#classA_1f2"),
+            ("classA_1g", "classA.html#classA_1g"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        // left over from a run that had symbols starting with z
+        std::fs::create_dir_all(dir.join("search")).unwrap();
+        std::fs::write(dir.join("search").join("z.js"), "").unwrap();
+
+        write_search_index(&dir, &compounds, &ref_to_path).unwrap();
+
+        let keys = read_data_script(&dir, "search/keys.js");
+        assert_eq!(keys, json::array!["a", "f", "g"]);
+        assert_eq!(
+            read_data_script(&dir, "search/f.js"),
+            json::array![["A::f", "function", "Does f1.", "classA.html#classA-f"]]
+        );
+        assert_eq!(
+            read_data_script(&dir, "search/g.js"),
+            json::array![["A::g", "function", "Does g.", "classA.html#classA_1g"]]
+        );
+        assert!(!dir.join("search").join("z.js").exists());
+    }
+
+    #[test]
+    fn text_index_maps_stemmed_terms_to_documents() {
+        let dir = crate::test_dir("text-index");
+        let compounds = [parse_class(&dir)];
+        let ref_to_path: HashMap<String, String> = [
+            ("classA_1f1", "classA.html#classA_1f1"),
+            ("classA_1f2", "classA.html#classA_1f2"),
+            ("classA_1g", "classA.html#classA_1g"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let text_dir = dir.join("search").join("text");
+        std::fs::create_dir_all(&text_dir).unwrap();
+        std::fs::write(text_dir.join("d1.js"), "").unwrap();
+
+        write_text_index(&dir, &compounds, &ref_to_path).unwrap();
+
+        // "does" is a stop word and "g" too short, which leaves f1 and f2
+        assert_eq!(
+            read_data_script(&dir, "search/text/tf.js"),
+            json::array![3, { "f1": [0, 1], "f2": [1, 1] }]
+        );
+        let documents = read_data_script(&dir, "search/text/d0.js");
+        assert_eq!(documents.len(), 3);
+        assert_eq!(
+            documents[0],
+            json::array!["A::f", "member", "classA.html#classA_1f1", 1, "Does f1."]
+        );
+        assert!(!text_dir.join("d1.js").exists());
+    }
+
+    #[test]
+    fn strip_html_decodes_entities_and_separates_blocks() {
+        assert_eq!(
+            strip_html("<p>a&lt;b&amp;c</p><p>next&nbsp;one</p>"),
+            "a<b&c next one"
+        );
+    }
+}
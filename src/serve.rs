@@ -0,0 +1,351 @@
+use crate::Settings;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+// `doxyrs serve` builds once, then serves the output directory on localhost and polls the inputs
+// for changes. Rebuilds go through the build cache, so only affected pages are rendered again.
+// Pages poll VERSION_PATH and reload when the build version changes.
+
+const VERSION_PATH: &str = "/__doxyrs/version";
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// `load_settings` reads the settings again when the config file changes.
+pub fn run(
+    mut settings: Settings,
+    port: u16,
+    load_settings: impl Fn() -> Result<Settings, String>,
+) {
+    // build errors are reported by the build; keep serving what is there
+    crate::build(&settings);
+    settings.full_rebuild = false;
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("ERROR: could not listen on port {}: {}", port, e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Serving {} on http://localhost:{}/",
+        settings.html_dir.display(),
+        port
+    );
+
+    let version = Arc::new(AtomicU64::new(0));
+    {
+        let html_dir = settings.html_dir.clone();
+        let version = version.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let html_dir = html_dir.clone();
+                let version = version.clone();
+                std::thread::spawn(move || {
+                    // the browser going away mid-response is not worth reporting
+                    let _ = handle_request(stream, &html_dir, &version);
+                });
+            }
+        });
+    }
+
+    let (mut watched, mut output) = watched_paths(&settings);
+    let mut snapshot = take_snapshot(&watched, &output);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let mut current = take_snapshot(&watched, &output);
+        if current == snapshot {
+            continue;
+        }
+        // doxygen writes its output over a while, wait until it is done
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let next = take_snapshot(&watched, &output);
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+        let config_changed = settings.config_file.as_ref().is_some_and(|config_file| {
+            let config_file = config_file.canonicalize().unwrap_or(config_file.clone());
+            let entry = |s: &Snapshot| s.iter().find(|f| f.0 == config_file).cloned();
+            entry(&snapshot) != entry(&current)
+        });
+        snapshot = current;
+
+        if config_changed {
+            match load_settings() {
+                Ok(mut new_settings) => {
+                    println!("Config changed, rebuilding");
+                    // the server keeps serving the directory it started with
+                    new_settings.html_dir = settings.html_dir.clone();
+                    new_settings.live_reload = true;
+                    new_settings.full_rebuild = false;
+                    settings = new_settings;
+                    (watched, output) = watched_paths(&settings);
+                    snapshot = take_snapshot(&watched, &output);
+                }
+                Err(message) => {
+                    println!("{}", message);
+                    continue;
+                }
+            }
+        } else {
+            println!("Input changed, rebuilding");
+        }
+        crate::build(&settings);
+        version.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+type Snapshot = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+/// Everything a build reads: the XML, images below the source directory, the theme and the
+/// config file. Returns them with the output directory, which is left out when it is inside one
+/// of them, so a build does not trigger the next one.
+fn watched_paths(settings: &Settings) -> (Vec<PathBuf>, PathBuf) {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or(path.to_owned());
+    let mut watched = vec![
+        canonical(&settings.xml_dir),
+        canonical(&settings.source_dir),
+    ];
+    watched.extend(settings.theme_dir.as_deref().map(canonical));
+    watched.extend(settings.config_file.as_deref().map(canonical));
+    (watched, canonical(&settings.html_dir))
+}
+
+/// Modification time and size of the given files and of every file below the given
+/// directories, except those in `excluded`.
+fn take_snapshot(paths: &[PathBuf], excluded: &Path) -> Snapshot {
+    let mut files = vec![];
+    let mut pending = vec![];
+    for path in paths {
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => {
+                files.push((path.clone(), metadata.modified().ok(), metadata.len()))
+            }
+            _ => pending.push(path.clone()),
+        }
+    }
+    while let Some(dir) = pending.pop() {
+        if dir == excluded {
+            continue;
+        }
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.push((entry.path(), metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn handle_request(stream: TcpStream, html_dir: &Path, version: &AtomicU64) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // skip the headers
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    let mut stream = reader.into_inner();
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return respond(&mut stream, "400 Bad Request", "text/plain", b"bad request"),
+    };
+    if method != "GET" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"method not allowed",
+        );
+    }
+
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    if path == VERSION_PATH {
+        let body = version.load(Ordering::SeqCst).to_string();
+        return respond(&mut stream, "200 OK", "text/plain", body.as_bytes());
+    }
+
+    match resolve_path(html_dir, path) {
+        Some(file_name) => match std::fs::read(&file_name) {
+            Ok(content) => respond(&mut stream, "200 OK", content_type(&file_name), &content),
+            Err(_) => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+        },
+        None => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+/// Maps a URL path to a file in the output directory. Paths leaving it are rejected.
+fn resolve_path(html_dir: &Path, url_path: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(url_path)
+        .decode_utf8()
+        .ok()?;
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    let mut file_name = html_dir.join(relative);
+    if file_name.is_dir() {
+        file_name.push("index.html");
+    }
+    Some(file_name)
+}
+
+fn content_type(file_name: &Path) -> &'static str {
+    match file_name.extension().and_then(|e| e.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::io::Read;
+
+    #[test]
+    fn paths_outside_the_output_directory_are_rejected() {
+        let html_dir = crate::test_dir("serve-paths");
+        std::fs::create_dir_all(html_dir.join("sub")).unwrap();
+        assert_eq!(
+            resolve_path(&html_dir, "/a%20b.html"),
+            Some(html_dir.join("a b.html"))
+        );
+        assert_eq!(
+            resolve_path(&html_dir, "/"),
+            Some(html_dir.join("index.html"))
+        );
+        assert_eq!(
+            resolve_path(&html_dir, "/sub/"),
+            Some(html_dir.join("sub/index.html"))
+        );
+        assert_eq!(
+            resolve_path(&html_dir, "//etc/passwd"),
+            Some(html_dir.join("etc/passwd"))
+        );
+        for path in &[
+            "/../secret",
+            "/sub/../../secret",
+            "/%2e%2e/secret",
+            "/sub/%2E%2E/index.html",
+            "/./index.html",
+            "/%ff.html",
+        ] {
+            assert_eq!(resolve_path(&html_dir, path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn snapshots_leave_out_the_output_directory() {
+        let dir = crate::test_dir("serve-watch");
+        std::fs::create_dir_all(dir.join("xml")).unwrap();
+        std::fs::create_dir_all(dir.join("html")).unwrap();
+        let config_file = dir.join("doxyrs.toml");
+        std::fs::write(
+            &config_file,
+            "[input]\nsource = \".\"\nxml = \"xml\"\n[output]\ndir = \"html\"\n",
+        )
+        .unwrap();
+        let cli = crate::Cli::parse_from(["doxyrs", "--config", config_file.to_str().unwrap()]);
+        let settings = crate::load_settings(&cli).unwrap();
+
+        let (watched, output) = watched_paths(&settings);
+        let dir = dir.canonicalize().unwrap();
+        assert_eq!(output, dir.join("html"));
+        assert!(watched.contains(&dir.join("doxyrs.toml")));
+
+        std::fs::write(dir.join("xml/index.xml"), "").unwrap();
+        std::fs::write(dir.join("html/index.html"), "").unwrap();
+        let snapshot = take_snapshot(&watched, &output);
+        let files = snapshot.iter().map(|f| f.0.clone()).collect::<Vec<_>>();
+        // the config file is both below the source directory and watched by itself
+        assert_eq!(files, [dir.join("doxyrs.toml"), dir.join("xml/index.xml")]);
+
+        std::fs::write(dir.join("html/index.html"), "changed").unwrap();
+        assert_eq!(take_snapshot(&watched, &output), snapshot);
+        std::fs::write(dir.join("xml/index.xml"), "changed").unwrap();
+        assert_ne!(take_snapshot(&watched, &output), snapshot);
+    }
+
+    /// Sends `request` to a server for `html_dir` at build version 3 and returns the response.
+    fn get(html_dir: &Path, request: &str) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        handle_request(stream, html_dir, &AtomicU64::new(3)).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn requests_are_answered_from_the_output_directory() {
+        let html_dir = crate::test_dir("serve-requests");
+        std::fs::write(html_dir.join("index.html"), "<p>hi</p>").unwrap();
+
+        let response = get(&html_dir, "GET /?q=x HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(response.ends_with("\r\n\r\n<p>hi</p>"));
+
+        let response = get(&html_dir, "GET /__doxyrs/version HTTP/1.1\r\n\r\n");
+        assert!(response.ends_with("\r\n\r\n3"));
+        let response = get(&html_dir, "GET /../index.html HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        let response = get(&html_dir, "GET /missing.html HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        let response = get(&html_dir, "POST / HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed"));
+    }
+}
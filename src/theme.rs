@@ -0,0 +1,271 @@
+use crate::error::DoxyError;
+use crate::hash::{content_hash, hashed_file_name};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use tera::Tera;
+
+// The default templates and static files are compiled into the binary. A theme directory can
+// replace any of them and add more:
+//
+// <theme>/templates/*.html  override the default template of the same name. Every default is also
+//                           available as "default/<name>", so an override can extend it and only
+//                           replace some of its blocks.
+// <theme>/static/*          copied to the output's static directory. Files with the name of a
+//                           default asset replace it, other .css and .js files are included by
+//                           base.html after the defaults.
+//
+// Static files are written as `name.<content hash>.ext`, so browsers never use a stale cached
+// copy. Templates look up the actual URLs in `assets`, e.g. `assets["style.css"]`.
+
+const TEMPLATES: &[(&str, &str)] = &[
+    ("base.html", include_str!("../templates/base.html")),
+    ("sidebar.html", include_str!("../templates/sidebar.html")),
+    ("header.html", include_str!("../templates/header.html")),
+    ("footer.html", include_str!("../templates/footer.html")),
+    ("macros.html", include_str!("../templates/macros.html")),
+    ("file.html", include_str!("../templates/file.html")),
+    ("page.html", include_str!("../templates/page.html")),
+    ("scope.html", include_str!("../templates/scope.html")),
+    ("overview.html", include_str!("../templates/overview.html")),
+];
+
+const STATIC_FILES: &[(&str, &[u8])] = &[
+    ("style.css", include_bytes!("../static/style.css")),
+    ("script.js", include_bytes!("../static/script.js")),
+];
+
+/// Also returns a hash over all template sources, so the build cache notices theme changes.
+pub fn load_templates(theme_dir: Option<&Path>) -> Result<(Tera, String), DoxyError> {
+    let mut templates = vec![];
+    for (name, content) in TEMPLATES {
+        templates.push((format!("default/{}", name), content.to_string()));
+        templates.push((name.to_string(), content.to_string()));
+    }
+    if let Some(theme_dir) = theme_dir {
+        let template_dir = theme_dir.join("templates");
+        if template_dir.exists() {
+            for entry in
+                std::fs::read_dir(&template_dir).map_err(|e| DoxyError::io(&template_dir, e))?
+            {
+                let path = entry.map_err(|e| DoxyError::io(&template_dir, e))?.path();
+                if path.extension().is_none_or(|e| e != "html") {
+                    continue;
+                }
+                let content =
+                    std::fs::read_to_string(&path).map_err(|e| DoxyError::io(&path, e))?;
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                // later entries replace earlier ones of the same name
+                templates.push((name, content));
+            }
+        }
+    }
+
+    let mut all_sources = vec![];
+    for (name, content) in &templates {
+        all_sources.extend_from_slice(name.as_bytes());
+        all_sources.push(0);
+        all_sources.extend_from_slice(content.as_bytes());
+        all_sources.push(0);
+    }
+    let hash = content_hash(&all_sources);
+
+    let mut tera = Tera::default();
+    tera.add_raw_templates(templates)
+        .map_err(|source| DoxyError::Render {
+            file: theme_dir
+                .map(|d| d.join("templates"))
+                .unwrap_or_else(|| "templates".into()),
+            source,
+        })?;
+    Ok((tera, hash))
+}
+
+/// URLs of the static files, relative to the output directory.
+#[derive(Default)]
+pub struct Assets {
+    /// Original file name -> URL
+    pub urls: BTreeMap<String, String>,
+    /// Additional stylesheets and scripts of the theme
+    pub extra_css: Vec<String>,
+    pub extra_js: Vec<String>,
+}
+
+/// Writes the built-in static files, the theme's and the logo. Files of earlier runs that
+/// are not needed any more are removed.
+pub fn write_static_files(
+    html_dir: &Path,
+    theme_dir: Option<&Path>,
+    logo: Option<&Path>,
+) -> std::io::Result<Assets> {
+    let target_dir = html_dir.join("static");
+    std::fs::create_dir_all(&target_dir)?;
+
+    let mut files = BTreeMap::<String, Vec<u8>>::new();
+    for (name, content) in STATIC_FILES {
+        files.insert(name.to_string(), content.to_vec());
+    }
+
+    let mut assets = Assets::default();
+    if let Some(source_dir) = theme_dir.map(|d| d.join("static")) {
+        if source_dir.exists() {
+            let mut entries = std::fs::read_dir(source_dir)?.collect::<Result<Vec<_>, _>>()?;
+            entries.sort_by_key(|entry| entry.file_name());
+            for entry in entries {
+                let from_path = entry.path();
+                if !from_path.is_file() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let is_default = files.contains_key(&name);
+                files.insert(name.clone(), std::fs::read(&from_path)?);
+                if is_default {
+                    continue;
+                }
+                if name.ends_with(".css") {
+                    assets.extra_css.push(name);
+                } else if name.ends_with(".js") {
+                    assets.extra_js.push(name);
+                }
+            }
+        }
+    }
+    if let Some(logo) = logo {
+        let name = logo.file_name().unwrap_or_default().to_string_lossy();
+        files.insert(name.into_owned(), std::fs::read(logo)?);
+    }
+
+    let mut written = HashSet::new();
+    for (name, content) in files {
+        let hashed_name = hashed_file_name(&name, &content);
+        let path = target_dir.join(&hashed_name);
+        if !path.exists() {
+            std::fs::write(&path, &content)?;
+        }
+        assets.urls.insert(name, format!("static/{}", hashed_name));
+        written.insert(hashed_name);
+    }
+    for url in assets
+        .extra_css
+        .iter_mut()
+        .chain(assets.extra_js.iter_mut())
+    {
+        *url = assets.urls[url.as_str()].clone();
+    }
+
+    for entry in std::fs::read_dir(&target_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !written.contains(&name) {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(assets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn theme_templates_replace_the_defaults_of_the_same_name() {
+        let theme_dir = crate::test_dir("theme-templates");
+        write(&theme_dir.join("templates/footer.html"), "custom footer");
+        write(
+            &theme_dir.join("templates/page.html"),
+            "{% extends \"default/page.html\" %}",
+        );
+        write(&theme_dir.join("templates/notes.txt"), "ignored");
+
+        let (tera, theme_hash) = load_templates(Some(&theme_dir)).unwrap();
+        let context = tera::Context::new();
+        assert_eq!(
+            tera.render("footer.html", &context).unwrap(),
+            "custom footer"
+        );
+        assert_ne!(
+            tera.render("default/footer.html", &context).unwrap(),
+            "custom footer"
+        );
+        assert!(tera.get_template("page.html").is_ok());
+        assert!(tera.get_template("notes.txt").is_err());
+
+        let (_, default_hash) = load_templates(None).unwrap();
+        assert_ne!(theme_hash, default_hash);
+        assert_eq!(load_templates(None).unwrap().1, default_hash);
+    }
+
+    #[test]
+    fn broken_theme_templates_name_the_theme() {
+        let theme_dir = crate::test_dir("theme-broken");
+        write(&theme_dir.join("templates/footer.html"), "{% if %}");
+        match load_templates(Some(&theme_dir)) {
+            Err(DoxyError::Render { file, .. }) => assert_eq!(file, theme_dir.join("templates")),
+            other => panic!("unexpected {:?}", other.map(|(_, hash)| hash)),
+        }
+    }
+
+    #[test]
+    fn theme_static_files_replace_or_extend_the_defaults() {
+        let theme_dir = crate::test_dir("theme-static");
+        let html_dir = crate::test_dir("theme-static-out");
+        write(&theme_dir.join("static/style.css"), "body {}");
+        write(&theme_dir.join("static/b.js"), "b();");
+        write(&theme_dir.join("static/a.css"), "a {}");
+        write(&theme_dir.join("static/a.js"), "a();");
+        write(&theme_dir.join("static/font.woff"), "font");
+
+        let assets = write_static_files(&html_dir, Some(&theme_dir), None).unwrap();
+        let style = &assets.urls["style.css"];
+        assert_eq!(
+            std::fs::read_to_string(html_dir.join(style)).unwrap(),
+            "body {}"
+        );
+        assert!(assets.urls.contains_key("script.js"));
+        assert!(assets.urls.contains_key("font.woff"));
+        assert_eq!(assets.extra_css, vec![assets.urls["a.css"].clone()]);
+        assert_eq!(
+            assets.extra_js,
+            vec![assets.urls["a.js"].clone(), assets.urls["b.js"].clone()]
+        );
+    }
+
+    #[test]
+    fn static_files_of_earlier_runs_are_removed() {
+        let theme_dir = crate::test_dir("theme-stale");
+        let html_dir = crate::test_dir("theme-stale-out");
+        write(&theme_dir.join("static/extra.css"), "a {}");
+        let first = write_static_files(&html_dir, Some(&theme_dir), None).unwrap();
+        let old_extra = html_dir.join(&first.urls["extra.css"]);
+        assert!(old_extra.exists());
+        assert_eq!(
+            first.urls["style.css"],
+            format!(
+                "static/{}",
+                hashed_file_name("style.css", STATIC_FILES[0].1)
+            )
+        );
+
+        write(&theme_dir.join("static/extra.css"), "b {}");
+        let second = write_static_files(&html_dir, Some(&theme_dir), None).unwrap();
+        assert_ne!(first.urls["extra.css"], second.urls["extra.css"]);
+        assert!(!old_extra.exists());
+        assert_eq!(first.urls["style.css"], second.urls["style.css"]);
+        let mut names = std::fs::read_dir(html_dir.join("static"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        let mut expected = second
+            .urls
+            .values()
+            .map(|url| url.trim_start_matches("static/").to_string())
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+}
@@ -81,4 +81,342 @@ if (my_link !== undefined) {
         block: 'center',
         inline: 'center'
     });
-}
\ No newline at end of file
+}
+
+// search
+//
+// The indices are sharded (see src/search.rs). Shards are loaded as scripts on demand, since
+// fetch() is not available for pages opened via file://.
+let searchData = {};
+
+function searchDataLoaded(path, data) {
+    searchData[path].data = data;
+    for (let callback of searchData[path].callbacks) {
+        callback(data);
+    }
+    searchData[path].callbacks = [];
+}
+
+function loadSearchData(path, callback) {
+    let entry = searchData[path];
+    if (entry === undefined) {
+        entry = searchData[path] = { data: undefined, callbacks: [] };
+        let script = document.createElement("script");
+        script.src = pathToRoot + path;
+        script.onerror = function () {
+            searchDataLoaded(path, null);
+        };
+        document.head.appendChild(script);
+    }
+    if (entry.data === undefined) {
+        entry.callbacks.push(callback);
+    } else {
+        callback(entry.data);
+    }
+}
+
+function loadAllSearchData(paths, callback) {
+    let results = {};
+    let remaining = paths.length;
+    if (remaining == 0) {
+        callback(results);
+    }
+    for (let path of paths) {
+        loadSearchData(path, function (data) {
+            results[path] = data;
+            remaining -= 1;
+            if (remaining == 0) {
+                callback(results);
+            }
+        });
+    }
+}
+
+// must match shard_key in src/search.rs
+function shardKey(name) {
+    let c = name.charAt(0).toLowerCase();
+    return /[a-z0-9]/.test(c) ? c : "_";
+}
+
+// Returns a score (lower is better) or undefined if `name` does not match `query`. Exact and
+// prefix matches come first, then fuzzy matches where the query characters appear in order.
+function matchScore(name, query) {
+    let lower = name.toLowerCase();
+    let unqualified = lower.substring(lower.lastIndexOf("::") + 2);
+    if (unqualified == query || lower == query) {
+        return 0;
+    }
+    if (unqualified.startsWith(query)) {
+        return 1;
+    }
+    if (lower.startsWith(query)) {
+        return 2;
+    }
+    if (lower.includes(query)) {
+        return 3;
+    }
+    let gaps = 0;
+    let pos = 0;
+    for (let c of query) {
+        let found = lower.indexOf(c, pos);
+        if (found < 0) {
+            return undefined;
+        }
+        if (found > pos) {
+            gaps += 1;
+        }
+        pos = found + 1;
+    }
+    return 4 + gaps;
+}
+
+function createResult(href, title, kind) {
+    let link = document.createElement("a");
+    link.href = pathToRoot + href;
+    let name = document.createElement("span");
+    name.className = "search-name";
+    name.textContent = title;
+    let kindSpan = document.createElement("span");
+    kindSpan.className = "search-kind";
+    kindSpan.textContent = kind;
+    link.appendChild(name);
+    link.appendChild(kindSpan);
+    return link;
+}
+
+// Incremented whenever the query changes. Shards load asynchronously, so callbacks compare it to
+// the generation they were started in and drop results for outdated queries.
+let searchGeneration = 0;
+
+function searchSymbols(query, results, generation) {
+    let name = query.substring(query.lastIndexOf("::") + 2);
+    if (name.length == 0) {
+        return;
+    }
+    let showMatches = function (shards, searchedEverywhere) {
+        if (generation != searchGeneration) {
+            return;
+        }
+        let matches = [];
+        for (let entries of shards) {
+            for (let entry of entries || []) {
+                let score = matchScore(entry[0], query);
+                if (score !== undefined) {
+                    matches.push([score, entry]);
+                }
+            }
+        }
+        matches.sort(function (a, b) {
+            return a[0] - b[0] || a[1][0].length - b[1][0].length;
+        });
+        results.innerHTML = "";
+        for (let match of matches.slice(0, 50)) {
+            let entry = match[1];
+            let link = createResult(entry[3], entry[0], entry[1]);
+            if (entry[2]) {
+                let brief = document.createElement("span");
+                brief.className = "search-brief";
+                brief.textContent = entry[2];
+                link.appendChild(brief);
+            }
+            results.appendChild(link);
+        }
+        if (!searchedEverywhere) {
+            let button = document.createElement("button");
+            button.type = "button";
+            button.className = "search-everywhere";
+            button.textContent = "Search all symbols";
+            button.addEventListener("click", searchEverywhere);
+            results.appendChild(button);
+        }
+    };
+    // Substring and fuzzy matches can be in any shard. Loading all of them means loading the
+    // whole index, so that only happens when asked for.
+    let searchEverywhere = function () {
+        loadSearchData("search/keys.js", function (keys) {
+            let paths = (keys || []).map(function (key) {
+                return "search/" + key + ".js";
+            });
+            loadAllSearchData(paths, function (shards) {
+                showMatches(Object.values(shards), true);
+            });
+        });
+    };
+    // exact and prefix matches are in the shard of the name's first letter
+    loadSearchData("search/" + shardKey(name) + ".js", function (entries) {
+        showMatches([entries], false);
+    });
+}
+
+// must match STOP_WORDS in src/search.rs
+const stopWords = new Set([
+    "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any",
+    "are", "as", "at", "be", "because", "been", "before", "being", "below", "between", "both",
+    "but", "by", "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few",
+    "for", "from", "further", "had", "has", "have", "having", "he", "her", "here", "hers", "him",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself", "just", "may", "me",
+    "more", "most", "must", "my", "no", "nor", "not", "now", "of", "off", "on", "once", "only",
+    "or", "other", "our", "out", "over", "own", "same", "she", "should", "so", "some", "such",
+    "than", "that", "the", "their", "them", "then", "there", "these", "they", "this", "those",
+    "through", "to", "too", "under", "until", "up", "very", "was", "we", "were", "what", "when",
+    "where", "which", "while", "who", "whom", "why", "will", "with", "would", "you", "your",
+]);
+
+// must match stem in src/search.rs, whose tests run this and tokenize against it
+function stem(w) {
+    let isVowel = function (c) {
+        return "aeiouy".includes(c);
+    };
+    if (w.endsWith("sses")) {
+        w = w.slice(0, -2);
+    } else if (w.endsWith("ies") && w.length > 4) {
+        w = w.slice(0, -3) + "y";
+    } else if (w.endsWith("s") && !w.endsWith("ss") && !w.endsWith("us") && !w.endsWith("is")
+        && w.length > 3) {
+        w = w.slice(0, -1);
+    }
+    for (let suffix of ["ing", "ed"]) {
+        if (w.endsWith(suffix)) {
+            let stemmed = w.slice(0, -suffix.length);
+            if (stemmed.length >= 3 && Array.from(stemmed).some(isVowel)) {
+                w = stemmed;
+                if (w.endsWith("at") || w.endsWith("bl") || w.endsWith("iz")) {
+                    w += "e";
+                } else {
+                    let last = w.charAt(w.length - 1);
+                    if (last == w.charAt(w.length - 2) && !isVowel(last) && !"lsz".includes(last)) {
+                        w = w.slice(0, -1);
+                    }
+                }
+            }
+            break;
+        }
+    }
+    if (w.endsWith("e") && w.length > 4) {
+        w = w.slice(0, -1);
+    }
+    return w;
+}
+
+// must match tokenize in src/search.rs
+function tokenize(text) {
+    return text.split(/[^A-Za-z0-9]+/)
+        .filter(function (word) { return word.length >= 2; })
+        .map(function (word) { return word.toLowerCase(); })
+        .filter(function (word) { return !stopWords.has(word); })
+        .map(stem);
+}
+
+// Shows `text` around the first matching word, with all matching words highlighted.
+function createSnippet(text, terms) {
+    let snippet = document.createElement("span");
+    snippet.className = "search-brief";
+    let words = text.split(/([^A-Za-z0-9]+)/);
+    let isMatch = function (word) {
+        return word.length >= 2 && terms.includes(stem(word.toLowerCase()));
+    };
+    let first = words.findIndex(isMatch);
+    let start = Math.max(0, first - 10);
+    let end = Math.min(words.length, start + 40);
+    if (start > 0) {
+        snippet.appendChild(document.createTextNode("…"));
+    }
+    for (let word of words.slice(start, end)) {
+        if (isMatch(word)) {
+            let mark = document.createElement("mark");
+            mark.textContent = word;
+            snippet.appendChild(mark);
+        } else {
+            snippet.appendChild(document.createTextNode(word));
+        }
+    }
+    if (end < words.length) {
+        snippet.appendChild(document.createTextNode("…"));
+    }
+    return snippet;
+}
+
+function searchText(query, results, generation) {
+    let terms = Array.from(new Set(tokenize(query)));
+    if (terms.length == 0) {
+        return;
+    }
+    let termShards = Array.from(new Set(terms.map(function (t) {
+        return "search/text/t" + shardKey(t) + ".js";
+    })));
+    loadAllSearchData(termShards, function (shards) {
+        // tf-idf, summed over the query terms. Documents containing all terms rank first.
+        let scores = new Map();
+        for (let term of terms) {
+            let shard = shards["search/text/t" + shardKey(term) + ".js"];
+            if (!shard || !shard[1][term]) {
+                continue;
+            }
+            let nDocuments = shard[0];
+            let postings = shard[1][term];
+            let idf = Math.log(1 + nDocuments / (postings.length / 2));
+            for (let i = 0; i < postings.length; i += 2) {
+                let score = scores.get(postings[i]) || [0, 0];
+                score[0] += 1;
+                score[1] += postings[i + 1] * idf;
+                scores.set(postings[i], score);
+            }
+        }
+        let ranked = Array.from(scores.entries()).sort(function (a, b) {
+            return b[1][0] - a[1][0] || b[1][1] - a[1][1];
+        }).slice(0, 20);
+        let docShards = Array.from(new Set(ranked.map(function (r) {
+            return "search/text/d" + Math.floor(r[0] / docsPerShard) + ".js";
+        })));
+        loadAllSearchData(docShards, function (docs) {
+            if (generation != searchGeneration) {
+                return;
+            }
+            results.innerHTML = "";
+            if (ranked.length == 0) {
+                return;
+            }
+            let heading = document.createElement("h4");
+            heading.textContent = "In descriptions";
+            results.appendChild(heading);
+            for (let r of ranked) {
+                let shard = docs["search/text/d" + Math.floor(r[0] / docsPerShard) + ".js"];
+                let doc = shard && shard[r[0] % docsPerShard];
+                if (!doc) {
+                    continue;
+                }
+                let link = createResult(doc[2], doc[0], doc[1]);
+                link.appendChild(createSnippet(doc[4], terms));
+                results.appendChild(link);
+            }
+        });
+    });
+}
+
+// must match DOCS_PER_SHARD in src/search.rs
+const docsPerShard = 500;
+
+let searchBox = document.createElement("input");
+searchBox.id = "search";
+searchBox.type = "search";
+searchBox.placeholder = "Search…";
+let searchResults = document.createElement("div");
+searchResults.id = "search-results";
+let symbolResults = document.createElement("div");
+let textResults = document.createElement("div");
+searchResults.appendChild(symbolResults);
+searchResults.appendChild(textResults);
+searchBox.addEventListener("input", function () {
+    let query = searchBox.value.trim().toLowerCase();
+    searchGeneration += 1;
+    symbolResults.innerHTML = "";
+    textResults.innerHTML = "";
+    if (query.length == 0) {
+        return;
+    }
+    searchSymbols(query, symbolResults, searchGeneration);
+    searchText(query, textResults, searchGeneration);
+});
+let sidebarTitle = sidebar.querySelector("h1");
+sidebar.insertBefore(searchResults, sidebarTitle.nextSibling);
+sidebar.insertBefore(searchBox, searchResults);